@@ -6,24 +6,49 @@
 //
 // http://opensource.org/licenses/mit-license.php
 
+#[cfg(not(feature = "stub"))]
 use std::fs::read_dir;
+#[cfg(not(feature = "stub"))]
 use std::path::PathBuf;
 use std::{env, path::Path};
 
+#[cfg(not(feature = "stub"))]
 use ctranslate2_src_build_support::dnnl::build_dnnl;
+#[cfg(not(feature = "stub"))]
 use ctranslate2_src_build_support::download::download_helper;
+#[cfg(not(feature = "stub"))]
 use ctranslate2_src_build_support::file_changes::watch_dir_recursively;
 
+#[cfg(not(feature = "stub"))]
 use ctranslate2_src_build_support::native::cuda_root;
+#[cfg(not(feature = "stub"))]
 use ctranslate2_src_build_support::windows_crt_patch::patch_cmake_runtime_flags;
+#[cfg(not(feature = "stub"))]
 use ctranslate2_src_build_support::{Os, export, link_libraries, native::build_native};
+#[cfg(not(feature = "stub"))]
 use ctranslate2_src_build_support::{link_dynamic_libraries, submodules};
 
+/// Builds the wrapper against the fake CTranslate2 headers in `stub/include` without
+/// downloading, building or linking CTranslate2.
+#[cfg(feature = "stub")]
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=cpp");
+    println!("cargo:rerun-if-changed=include");
+    println!("cargo:rerun-if-changed=stub");
+    build_wrapper(Path::new("stub/include"));
+}
+
+#[cfg(not(feature = "stub"))]
 fn main() {
     if cfg!(feature = "export-vendor") {
         export(&lib_path, &modules, &modules2);
     }
 
+    build_wrapper(&include_path);
+}
+
+fn build_wrapper(include_path: &Path) {
     let mut builder = cc::Build::new();
     builder
         .cpp(true)
@@ -35,16 +60,6 @@ fn main() {
         .flag_if_supported("-Wextra")
         .compile("translator_wrapper");
 
-    // Bindgen
-    let bindings = bindgen::Builder::default()
-        .header("include/translator_wrapper.h")
-        .clang_args(&["-x", "c++", "-std=c++17"])
-        .blocklist_item("_LIBCPP_.*")
-        .generate()
-        .expect("Unable to generate bindings");
-
     let out_path = std::path::PathBuf::from(env::var("OUT_DIR").unwrap());
-    bindings
-        .write_to_file(out_path.join("translator_bindings.rs"))
-        .expect("Couldn't write bindings!");
+    std::fs::copy("bindings.rs", out_path.join("translator_bindings.rs")).unwrap();
 }
@@ -102,6 +102,42 @@ float translation_result_score(const CTranslationResult *result) {
   return result->tr->score();
 }
 
+size_t translation_result_hypothesis_size(const CTranslationResult *result,
+                                          size_t hypothesis) {
+  return result->tr->hypotheses[hypothesis].size();
+}
+
+const char *translation_result_hypothesis_at(const CTranslationResult *result,
+                                             size_t hypothesis, size_t idx) {
+  return result->tr->hypotheses[hypothesis][idx].c_str();
+}
+
+size_t translation_result_num_scores(const CTranslationResult *result) {
+  return result->tr->scores.size();
+}
+
+float translation_result_score_at(const CTranslationResult *result,
+                                  size_t hypothesis) {
+  return result->tr->scores[hypothesis];
+}
+
+size_t translation_result_attention_size(const CTranslationResult *result,
+                                         size_t hypothesis) {
+  if (hypothesis >= result->tr->attention.size())
+    return 0;
+  return result->tr->attention[hypothesis].size();
+}
+
+size_t translation_result_attention_row_size(const CTranslationResult *result,
+                                             size_t hypothesis, size_t row) {
+  return result->tr->attention[hypothesis][row].size();
+}
+
+const float *translation_result_attention_row(const CTranslationResult *result,
+                                              size_t hypothesis, size_t row) {
+  return result->tr->attention[hypothesis][row].data();
+}
+
 inline std::vector<std::vector<std::string>>
 to_string_vector(const char ***source, size_t num_sentences) {
   std::vector<std::vector<std::string>> result;
@@ -168,9 +204,13 @@ CTranslationResult **translator_translate_batch(
       (batch_type == 1) ? ctranslate2::BatchType::Tokens
                         : ctranslate2::BatchType::Examples;
 
-  std::vector<ctranslate2::TranslationResult> results =
-      translator->translator->translate_batch(cpp_source, cpp_options,
-                                              max_batch_size, cpp_batch_type);
+  std::vector<ctranslate2::TranslationResult> results;
+  try {
+    results = translator->translator->translate_batch(
+        cpp_source, cpp_options, max_batch_size, cpp_batch_type);
+  } catch (...) {
+    return nullptr;
+  }
 
   CTranslationResult **c_results = new CTranslationResult *[results.size()];
   for (size_t i = 0; i < results.size(); ++i) {
@@ -206,10 +246,14 @@ CTranslationResult **translator_translate_batch_with_target_prefix(
       (batch_type == 1) ? ctranslate2::BatchType::Tokens
                         : ctranslate2::BatchType::Examples;
 
-  std::vector<ctranslate2::TranslationResult> results =
-      translator->translator->translate_batch(cpp_source, cpp_tprefixes,
-                                              cpp_options, max_batch_size,
-                                              cpp_batch_type);
+  std::vector<ctranslate2::TranslationResult> results;
+  try {
+    results = translator->translator->translate_batch(
+        cpp_source, cpp_tprefixes, cpp_options, max_batch_size,
+        cpp_batch_type);
+  } catch (...) {
+    return nullptr;
+  }
 
   CTranslationResult **c_results = new CTranslationResult *[results.size()];
   for (size_t i = 0; i < results.size(); ++i) {
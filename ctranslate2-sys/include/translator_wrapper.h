@@ -69,6 +69,20 @@ const char *translation_result_output_at(const CTranslationResult *result,
 size_t translation_result_output_size(const CTranslationResult *result);
 float translation_result_score(const CTranslationResult *result);
 
+size_t translation_result_hypothesis_size(const CTranslationResult *result,
+                                          size_t hypothesis);
+const char *translation_result_hypothesis_at(const CTranslationResult *result,
+                                             size_t hypothesis, size_t idx);
+size_t translation_result_num_scores(const CTranslationResult *result);
+float translation_result_score_at(const CTranslationResult *result,
+                                  size_t hypothesis);
+size_t translation_result_attention_size(const CTranslationResult *result,
+                                         size_t hypothesis);
+size_t translation_result_attention_row_size(const CTranslationResult *result,
+                                             size_t hypothesis, size_t row);
+const float *translation_result_attention_row(const CTranslationResult *result,
+                                              size_t hypothesis, size_t row);
+
 #ifdef __cplusplus
 }
 #endif
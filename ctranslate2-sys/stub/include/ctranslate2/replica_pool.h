@@ -0,0 +1,16 @@
+// replica_pool.h
+//
+// Minimal stand-in for CTranslate2's replica_pool.h used by the `stub` feature.
+#pragma once
+
+#include <cstddef>
+
+namespace ctranslate2 {
+
+struct ReplicaPoolConfig {
+  size_t num_threads_per_replica = 0;
+  long max_queued_batches = 0;
+  int cpu_core_offset = -1;
+};
+
+} // namespace ctranslate2
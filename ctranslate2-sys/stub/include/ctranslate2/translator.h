@@ -0,0 +1,156 @@
+// translator.h
+//
+// Minimal stand-in for CTranslate2's translator.h used by the `stub` feature.
+//
+// The stub does not load a model. It only checks that the model directory
+// exists, and translating copies each source sentence to the output:
+//
+// - the end token `</s>` is dropped, the target prefix (if any) is prepended,
+//   and the result is truncated to `max_decoding_length`;
+// - `</s>` is appended again when `return_end_token` is set;
+// - `num_hypotheses` identical hypotheses are returned, scored
+//   `-0.1 * length - rank` when `return_scores` is set;
+// - with `return_attention`, target position `j` attends fully to source
+//   position `min(j, source_length - 1)`;
+// - a source containing the token `<stub-fail>` makes the whole batch throw.
+#pragma once
+
+#include <algorithm>
+#include <cstddef>
+#include <filesystem>
+#include <stdexcept>
+#include <string>
+#include <vector>
+
+#include "ctranslate2/replica_pool.h"
+
+namespace ctranslate2 {
+
+enum class Device { CPU, CUDA };
+
+enum class ComputeType {
+  DEFAULT,
+  AUTO,
+  FLOAT32,
+  INT8,
+  INT8_FLOAT32,
+  INT8_FLOAT16,
+  INT8_BFLOAT16,
+  INT16,
+  FLOAT16,
+  BFLOAT16,
+};
+
+enum class BatchType { Examples, Tokens };
+
+struct TranslationOptions {
+  size_t beam_size = 2;
+  float patience = 1;
+  float length_penalty = 1;
+  float coverage_penalty = 0;
+  float repetition_penalty = 1;
+  size_t no_repeat_ngram_size = 0;
+  bool disable_unk = false;
+  float prefix_bias_beta = 0;
+  bool return_end_token = false;
+  size_t max_input_length = 1024;
+  size_t max_decoding_length = 256;
+  size_t min_decoding_length = 1;
+  size_t sampling_topk = 1;
+  float sampling_topp = 1;
+  float sampling_temperature = 1;
+  bool use_vmap = false;
+  size_t num_hypotheses = 1;
+  bool return_scores = false;
+  bool return_attention = false;
+  bool return_logits_vocab = false;
+  bool return_alternatives = false;
+  float min_alternative_expansion_prob = 0;
+  bool replace_unknowns = false;
+};
+
+struct TranslationResult {
+  std::vector<std::vector<std::string>> hypotheses;
+  std::vector<float> scores;
+  std::vector<std::vector<std::vector<float>>> attention;
+
+  const std::vector<std::string> &output() const {
+    if (hypotheses.empty())
+      throw std::runtime_error("This result is empty");
+    return hypotheses[0];
+  }
+
+  float score() const {
+    if (scores.empty())
+      throw std::runtime_error("This result has no scores");
+    return scores[0];
+  }
+
+  size_t num_hypotheses() const { return hypotheses.size(); }
+
+  bool has_scores() const { return !scores.empty(); }
+
+  bool has_attention() const { return !attention.empty(); }
+};
+
+class Translator {
+public:
+  Translator(const std::string &model_path, Device, ComputeType,
+             std::vector<int>, bool, const ReplicaPoolConfig &) {
+    if (!std::filesystem::is_directory(model_path))
+      throw std::invalid_argument("Unable to open the model directory " +
+                                  model_path);
+  }
+
+  std::vector<TranslationResult>
+  translate_batch(const std::vector<std::vector<std::string>> &source,
+                  const TranslationOptions &options, size_t = 0,
+                  BatchType = BatchType::Examples) {
+    return translate_batch(source, {}, options);
+  }
+
+  std::vector<TranslationResult>
+  translate_batch(const std::vector<std::vector<std::string>> &source,
+                  const std::vector<std::vector<std::string>> &target_prefix,
+                  const TranslationOptions &options, size_t = 0,
+                  BatchType = BatchType::Examples) {
+    std::vector<TranslationResult> results;
+    results.reserve(source.size());
+
+    for (size_t i = 0; i < source.size(); ++i) {
+      std::vector<std::string> tokens;
+      if (i < target_prefix.size())
+        tokens = target_prefix[i];
+      for (const auto &token : source[i]) {
+        if (token == "<stub-fail>")
+          throw std::runtime_error("Stub translation failure");
+        if (token != "</s>")
+          tokens.push_back(token);
+      }
+      if (tokens.size() > options.max_decoding_length)
+        tokens.resize(options.max_decoding_length);
+      if (options.return_end_token)
+        tokens.emplace_back("</s>");
+
+      TranslationResult result;
+      const size_t n = std::max<size_t>(options.num_hypotheses, 1);
+      for (size_t h = 0; h < n; ++h) {
+        result.hypotheses.push_back(tokens);
+        if (options.return_scores)
+          result.scores.push_back(-0.1f * tokens.size() - h);
+        if (options.return_attention) {
+          std::vector<std::vector<float>> attention(
+              tokens.size(), std::vector<float>(source[i].size(), 0));
+          for (size_t j = 0; j < tokens.size() && !source[i].empty(); ++j)
+            attention[j][std::min(j, source[i].size() - 1)] = 1;
+          result.attention.push_back(std::move(attention));
+        }
+      }
+      results.push_back(std::move(result));
+    }
+
+    return results;
+  }
+};
+
+} // namespace ctranslate2
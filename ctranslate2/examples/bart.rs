@@ -33,7 +33,7 @@
 //! Then, execute the sample code below with the following command:
 //!
 //! ```bash
-//! cargo run --example bart -- ./bart-large-cnn
+//! cargo run --example bart --features tokenizers -- ./bart-large-cnn
 //! ```
 //!
 
@@ -44,7 +44,7 @@ use std::time;
 use anyhow::Result;
 use clap::Parser;
 
-use ctranslate2::tokenizer::rust_tokenizers::SentenceTokenizer;
+use ctranslate2::tokenizer::auto;
 use ctranslate2::{Device, Translator2, TranslatorConfig};
 
 /// Summarize a file using a BART model.
@@ -73,7 +73,7 @@ fn main() -> Result<()> {
         TranslatorConfig::default()
     };
 
-    let t = Translator2::new(&args.path, &cfg, SentenceTokenizer::new(&args.path))?;
+    let t = Translator2::new(&args.path, &cfg, auto::load(&args.path)?)?;
 
     let source = BufReader::new(File::open(args.prompt)?).lines().try_fold(
         String::new(),
@@ -89,9 +89,8 @@ fn main() -> Result<()> {
     let res = t.translate_batch(&[source], Default::default())?;
     let elapsed = now.elapsed();
 
-    for (res, _) in res {
-        // Trim special tokens.
-        println!("{}", res.replace("<s>", ""));
+    for res in res {
+        println!("{}", res.text);
     }
     println!("Time taken: {elapsed:?}");
 
@@ -0,0 +1,189 @@
+//! Word alignment from attention.
+//!
+//! The attention returned with `return_attention` tells which source tokens the model looked
+//! at while generating each target token. [`align`] turns it into subword alignment pairs,
+//! with one of the [`AlignmentStrategy`] strategies, and [`to_words`] merges the subwords into
+//! words using the byte spans of the tokens: a token continues the word of the previous token
+//! unless whitespace separates them.
+//!
+//! [`Translator2::align_words`](crate::Translator2::align_words) does all the steps for a
+//! translation returned by
+//! [`translate_batch_with_spans`](crate::Translator2::translate_batch_with_spans). Pairs are
+//! `(source, target)` word indices, and [`to_pharaoh`] writes them in the Pharaoh format used
+//! by Moses and fast_align, e.g. `0-0 1-2 2-1`.
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use ctranslate2::alignment::{AlignmentStrategy, to_pharaoh};
+//! use ctranslate2::tokenizer::auto;
+//! use ctranslate2::{TranslationOptions, Translator2};
+//!
+//! # fn main() -> Result<()> {
+//! let path = "/path/to/model";
+//! let t = Translator2::new(path, &Default::default(), auto::load(path)?)?;
+//! let options = TranslationOptions {
+//!     return_attention: true,
+//!     ..Default::default()
+//! };
+//! let sources = ["Hello world!"];
+//! for (source, r) in sources.iter().zip(t.translate_batch_with_spans(&sources, options)?) {
+//!     let pairs = t.align_words(source, &r, AlignmentStrategy::Argmax)?;
+//!     println!("{}\t{}", r.text, to_pharaoh(&pairs));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::Result;
+
+use crate::Tokenizer;
+
+/// How target tokens are aligned to source tokens.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AlignmentStrategy {
+    /// Aligns every target token to the source token with the highest attention.
+    #[default]
+    Argmax,
+    /// Aligns every target token to all the source tokens with at least the given attention.
+    Threshold(f32),
+}
+
+/// Returns the `(source, target)` token pairs of an attention matrix of
+/// `[target_len][source_len]` weights, sorted.
+pub fn align(attention: &[Vec<f32>], strategy: AlignmentStrategy) -> Vec<(usize, usize)> {
+    let mut res = Vec::new();
+    for (target, row) in attention.iter().enumerate() {
+        match strategy {
+            AlignmentStrategy::Argmax => {
+                if let Some((source, _)) = row.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))
+                {
+                    res.push((source, target));
+                }
+            }
+            AlignmentStrategy::Threshold(threshold) => res.extend(
+                row.iter()
+                    .enumerate()
+                    .filter(|&(_, &w)| w >= threshold)
+                    .map(|(source, _)| (source, target)),
+            ),
+        }
+    }
+    res.sort_unstable();
+    res
+}
+
+/// Returns the index of the word of every token, given the byte span of every token in
+/// `text`. Tokens with an empty or whitespace span, such as special tokens, belong to no word.
+pub fn word_ids(text: &str, spans: &[(usize, usize)]) -> Vec<Option<usize>> {
+    let mut res = Vec::with_capacity(spans.len());
+    let mut words = 0;
+    let mut prev_end: Option<usize> = None;
+    for &(start, end) in spans {
+        let piece = text.get(start..end).unwrap_or_default();
+        if piece.trim().is_empty() {
+            res.push(None);
+            continue;
+        }
+        let new_word = match prev_end {
+            None => true,
+            Some(prev_end) => {
+                piece.starts_with(char::is_whitespace)
+                    || text
+                        .get(prev_end.min(start)..start)
+                        .is_none_or(|gap| gap.contains(char::is_whitespace))
+            }
+        };
+        if new_word {
+            words += 1;
+        }
+        res.push(Some(words - 1));
+        prev_end = Some(end);
+    }
+    res
+}
+
+/// Merges token pairs into word pairs, given the word of every source and target token.
+/// Tokens outside a word are dropped. The pairs are sorted and unique.
+pub fn to_words(
+    pairs: &[(usize, usize)],
+    source_words: &[Option<usize>],
+    target_words: &[Option<usize>],
+) -> Vec<(usize, usize)> {
+    let mut res = pairs
+        .iter()
+        .filter_map(|&(s, t)| {
+            Some((
+                source_words.get(s).copied()??,
+                target_words.get(t).copied()??,
+            ))
+        })
+        .collect::<Vec<_>>();
+    res.sort_unstable();
+    res.dedup();
+    res
+}
+
+/// Writes alignment pairs in the Pharaoh format: `i-j` pairs separated by spaces.
+pub fn to_pharaoh(pairs: &[(usize, usize)]) -> String {
+    pairs
+        .iter()
+        .map(|(s, t)| format!("{s}-{t}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Decodes target tokens, and returns the text with the byte span of every token in it. A
+/// token spans the text its decoding appends to the decoding of the previous tokens.
+pub fn decode_with_offsets<T: Tokenizer + ?Sized>(
+    tokenizer: &T,
+    tokens: &[String],
+) -> Result<(String, Vec<(usize, usize)>)> {
+    let text = tokenizer.decode(tokens.to_vec())?;
+    let mut spans = Vec::with_capacity(tokens.len());
+    let mut prev = 0;
+    for k in 1..=tokens.len() {
+        let mut end = tokenizer
+            .decode(tokens[..k].to_vec())?
+            .len()
+            .min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        let end = end.max(prev);
+        spans.push((prev, end));
+        prev = end;
+    }
+    Ok((text, spans))
+}
+
+#[test]
+fn align_words() {
+    let source = "unhappy cats";
+    let source_words = word_ids(source, &[(0, 2), (2, 7), (8, 12), (12, 12)]);
+    assert_eq!(source_words, [Some(0), Some(0), Some(1), None]);
+
+    let tokenizer = crate::tokenizer::whitespace::Tokenizer::new();
+    let tokens = ["chats", "tristes", "!"].map(String::from);
+    let (target, target_spans) = decode_with_offsets(&tokenizer, &tokens).unwrap();
+    assert_eq!(target, "chats tristes !");
+    assert_eq!(target_spans, [(0, 5), (5, 13), (13, 15)]);
+    let target_words = word_ids(&target, &target_spans);
+    assert_eq!(target_words, [Some(0), Some(1), Some(2)]);
+
+    let attention = vec![
+        vec![0.1, 0.1, 0.7, 0.1],
+        vec![0.4, 0.5, 0.0, 0.1],
+        vec![0.0, 0.0, 0.1, 0.9],
+    ];
+    let pairs = align(&attention, AlignmentStrategy::Argmax);
+    assert_eq!(pairs, [(1, 1), (2, 0), (3, 2)]);
+    let words = to_words(&pairs, &source_words, &target_words);
+    assert_eq!(to_pharaoh(&words), "0-1 1-0");
+
+    let pairs = align(&attention, AlignmentStrategy::Threshold(0.4));
+    assert_eq!(pairs, [(0, 1), (1, 1), (2, 0), (3, 2)]);
+    assert_eq!(
+        to_words(&pairs, &source_words, &target_words),
+        [(0, 1), (1, 0)]
+    );
+}
@@ -0,0 +1,274 @@
+//! A deterministic [`TranslationBackend`] for tests.
+//!
+//! [`MockBackend`] transforms the source tokens with a fixed rule instead of running a model:
+//! it can echo them, reverse them, or look every token up in a dictionary. Failures and
+//! latencies can be injected to exercise error handling and timeouts, and every batch it
+//! receives is recorded so tests can assert on what reached the backend.
+//!
+//! Like CTranslate2, the mock drops the end of sentence token `</s>` from its output unless
+//! `return_end_token` is set, prepends the target prefix when one is given, honors
+//! `max_decoding_length` and `num_hypotheses`, and only reports scores and attention if
+//! `return_scores` and `return_attention` are enabled. The attention of target position `j`
+//! is put entirely on source position `min(j, source_len - 1)`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::anyhow;
+
+use crate::TranslationOptions;
+use crate::backend::{BackendOutput, TranslationBackend};
+
+const END_TOKEN: &str = "</s>";
+
+/// How [`MockBackend`] maps source tokens to target tokens.
+#[derive(Clone, Debug)]
+pub enum MockMode {
+    /// Returns the source tokens unchanged.
+    Echo,
+    /// Returns the source tokens in reverse order.
+    Reverse,
+    /// Replaces every token found in the dictionary; unknown tokens are copied unchanged.
+    Dictionary(HashMap<String, String>),
+}
+
+/// When [`MockBackend`] should fail instead of translating.
+#[derive(Clone, Debug)]
+enum Failure {
+    Always,
+    OnCall(usize),
+    OnToken(String),
+}
+
+/// A [`TranslationBackend`] with canned, reproducible behavior.
+pub struct MockBackend {
+    mode: MockMode,
+    latency: Duration,
+    token_latency: Duration,
+    failures: Vec<Failure>,
+    calls: AtomicUsize,
+    batches: Mutex<Vec<Vec<Vec<String>>>>,
+}
+
+impl MockBackend {
+    /// Creates a backend using the given mode.
+    pub fn new(mode: MockMode) -> Self {
+        Self {
+            mode,
+            latency: Duration::ZERO,
+            token_latency: Duration::ZERO,
+            failures: Vec::new(),
+            calls: AtomicUsize::new(0),
+            batches: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Creates a backend returning the source tokens unchanged.
+    pub fn echo() -> Self {
+        Self::new(MockMode::Echo)
+    }
+
+    /// Creates a backend returning the source tokens in reverse order.
+    pub fn reverse() -> Self {
+        Self::new(MockMode::Reverse)
+    }
+
+    /// Creates a backend translating token by token using the given dictionary.
+    pub fn dictionary<I, K, V>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        Self::new(MockMode::Dictionary(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        ))
+    }
+
+    /// Sleeps for the given duration on every call.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sleeps for the given duration per source token on every call.
+    pub fn with_token_latency(mut self, latency: Duration) -> Self {
+        self.token_latency = latency;
+        self
+    }
+
+    /// Fails every call.
+    pub fn fail_always(mut self) -> Self {
+        self.failures.push(Failure::Always);
+        self
+    }
+
+    /// Fails the `n`-th call, counting from zero.
+    pub fn fail_on_call(mut self, n: usize) -> Self {
+        self.failures.push(Failure::OnCall(n));
+        self
+    }
+
+    /// Fails any call whose batch contains the given source token.
+    pub fn fail_on_token<S: Into<String>>(mut self, token: S) -> Self {
+        self.failures.push(Failure::OnToken(token.into()));
+        self
+    }
+
+    /// Returns the number of calls received so far, including failed ones.
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    /// Returns the source batches received so far, in call order.
+    pub fn batches(&self) -> Vec<Vec<Vec<String>>> {
+        self.batches.lock().unwrap().clone()
+    }
+
+    fn transform(&self, source: &[String]) -> Vec<String> {
+        let tokens = source.iter().filter(|t| t.as_str() != END_TOKEN);
+        match &self.mode {
+            MockMode::Echo => tokens.cloned().collect(),
+            MockMode::Reverse => tokens.rev().cloned().collect(),
+            MockMode::Dictionary(dict) => {
+                tokens.map(|t| dict.get(t).unwrap_or(t).clone()).collect()
+            }
+        }
+    }
+
+    fn run(
+        &self,
+        source: &[Vec<String>],
+        target_prefixes: Option<&[Vec<String>]>,
+        options: &TranslationOptions,
+    ) -> anyhow::Result<Vec<BackendOutput>> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        self.batches.lock().unwrap().push(source.to_vec());
+
+        let num_tokens: usize = source.iter().map(Vec::len).sum();
+        let delay = self.latency + self.token_latency * num_tokens as u32;
+        if !delay.is_zero() {
+            thread::sleep(delay);
+        }
+
+        for failure in &self.failures {
+            match failure {
+                Failure::Always => return Err(anyhow!("mock backend failure")),
+                Failure::OnCall(n) if *n == call => {
+                    return Err(anyhow!("mock backend failure on call {call}"));
+                }
+                Failure::OnToken(token) if source.iter().flatten().any(|t| t == token) => {
+                    return Err(anyhow!("mock backend failure on token {token:?}"));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(prefixes) = target_prefixes
+            && prefixes.len() != source.len()
+        {
+            return Err(anyhow!(
+                "expected {} target prefixes, got {}",
+                source.len(),
+                prefixes.len()
+            ));
+        }
+
+        Ok(source
+            .iter()
+            .enumerate()
+            .map(|(idx, tokens)| {
+                let mut output = target_prefixes.map(|p| p[idx].clone()).unwrap_or_default();
+                output.extend(self.transform(tokens));
+                output.truncate(options.max_decoding_length);
+                if options.return_end_token {
+                    output.push(END_TOKEN.to_owned());
+                }
+
+                let n = options.num_hypotheses.max(1);
+                let score = -0.1 * output.len() as f32;
+                let attention = if options.return_attention {
+                    let mut matrix = vec![vec![0.0; tokens.len()]; output.len()];
+                    for (j, row) in matrix.iter_mut().enumerate() {
+                        if let Some(cell) = row.get_mut(j.min(tokens.len().saturating_sub(1))) {
+                            *cell = 1.0;
+                        }
+                    }
+                    vec![matrix; n]
+                } else {
+                    Vec::new()
+                };
+                BackendOutput {
+                    hypotheses: vec![output; n],
+                    scores: if options.return_scores {
+                        (0..n).map(|i| score - i as f32).collect()
+                    } else {
+                        Vec::new()
+                    },
+                    attention,
+                }
+            })
+            .collect())
+    }
+}
+
+impl TranslationBackend for MockBackend {
+    fn translate_batch(
+        &self,
+        source: &[Vec<String>],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<BackendOutput>> {
+        self.run(source, None, &options)
+    }
+
+    fn translate_batch_with_prefixes(
+        &self,
+        source: &[Vec<String>],
+        target_prefixes: &[Vec<String>],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<BackendOutput>> {
+        self.run(source, Some(target_prefixes), &options)
+    }
+}
+
+#[test]
+fn mock_backend_modes_and_failures() {
+    let tokens = |s: &str| s.split(' ').map(str::to_owned).collect::<Vec<_>>();
+    let source = vec![tokens("a b </s>")];
+
+    let echo = MockBackend::echo();
+    let out = echo.translate_batch(&source, Default::default()).unwrap();
+    assert_eq!(out[0].output(), tokens("a b"));
+    assert_eq!(out[0].score(), None);
+
+    let dict = MockBackend::dictionary([("a", "x")]);
+    let options = TranslationOptions {
+        return_scores: true,
+        num_hypotheses: 2,
+        ..Default::default()
+    };
+    let out = dict.translate_batch(&source, options).unwrap();
+    assert_eq!(out[0].hypotheses, vec![tokens("x b"), tokens("x b")]);
+    assert_eq!(out[0].scores.len(), 2);
+
+    let failing = MockBackend::reverse().fail_on_call(1).fail_on_token("boom");
+    assert!(failing.translate_batch(&source, Default::default()).is_ok());
+    assert!(
+        failing
+            .translate_batch(&source, Default::default())
+            .is_err()
+    );
+    assert!(
+        failing
+            .translate_batch(&[tokens("boom")], Default::default())
+            .is_err()
+    );
+    assert_eq!(failing.calls(), 3);
+    assert_eq!(failing.batches()[2], vec![tokens("boom")]);
+}
@@ -0,0 +1,126 @@
+//! Abstraction over the engine that turns source tokens into target tokens.
+//!
+//! [`Translator2`](crate::Translator2) does not talk to CTranslate2 directly; it drives any
+//! type implementing [`TranslationBackend`]. The native [`Translator`] is the default backend,
+//! and [`mock::MockBackend`] provides a deterministic, dependency free replacement that can be
+//! used to unit test code built on top of [`Translator2`](crate::Translator2) without a model
+//! or a native build.
+//!
+//! ```
+//! use ctranslate2::Translator2;
+//! use ctranslate2::backend::mock::MockBackend;
+//! use ctranslate2::tokenizer::whitespace;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let t = Translator2::with_backend(MockBackend::reverse(), whitespace::Tokenizer::new());
+//! let res = t.translate_batch(&["a b c".to_owned()], Default::default())?;
+//! assert_eq!(res[0].text, "c b a");
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod mock;
+
+use crate::TranslationOptions;
+use crate::translator::{TranslationResult, Translator};
+
+/// Hypotheses produced for a single source sentence.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BackendOutput {
+    /// The n-best target token sequences, best first.
+    pub hypotheses: Vec<Vec<String>>,
+    /// Scores of the hypotheses. Empty unless `return_scores` was requested.
+    pub scores: Vec<f32>,
+    /// Attention of each hypothesis as a `[target_len][source_len]` matrix. Empty unless
+    /// `return_attention` was requested.
+    pub attention: Vec<Vec<Vec<f32>>>,
+}
+
+impl BackendOutput {
+    /// Returns the best hypothesis.
+    pub fn output(&self) -> &[String] {
+        self.hypotheses
+            .first()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the score of the best hypothesis, if scores were requested.
+    pub fn score(&self) -> Option<f32> {
+        self.scores.first().copied()
+    }
+}
+
+impl From<&TranslationResult> for BackendOutput {
+    fn from(result: &TranslationResult) -> Self {
+        Self {
+            hypotheses: result.hypotheses(),
+            scores: result.scores(),
+            attention: result.attention(),
+        }
+    }
+}
+
+/// An engine translating batches of tokenized sentences.
+pub trait TranslationBackend: Send + Sync {
+    /// Translates a batch of tokenized source sentences.
+    fn translate_batch(
+        &self,
+        source: &[Vec<String>],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<BackendOutput>>;
+
+    /// Translates a batch of tokenized source sentences, forcing each output to start with
+    /// the matching target prefix. The returned hypotheses include the prefix.
+    fn translate_batch_with_prefixes(
+        &self,
+        source: &[Vec<String>],
+        target_prefixes: &[Vec<String>],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<BackendOutput>>;
+}
+
+impl TranslationBackend for Translator {
+    fn translate_batch(
+        &self,
+        source: &[Vec<String>],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<BackendOutput>> {
+        Ok(Translator::translate_batch(self, source, options)?
+            .iter()
+            .map(BackendOutput::from)
+            .collect())
+    }
+
+    fn translate_batch_with_prefixes(
+        &self,
+        source: &[Vec<String>],
+        target_prefixes: &[Vec<String>],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<BackendOutput>> {
+        Ok(self
+            .translate_batch2(source, target_prefixes, options)?
+            .iter()
+            .map(BackendOutput::from)
+            .collect())
+    }
+}
+
+impl<B: TranslationBackend + ?Sized> TranslationBackend for Box<B> {
+    fn translate_batch(
+        &self,
+        source: &[Vec<String>],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<BackendOutput>> {
+        (**self).translate_batch(source, options)
+    }
+
+    fn translate_batch_with_prefixes(
+        &self,
+        source: &[Vec<String>],
+        target_prefixes: &[Vec<String>],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<BackendOutput>> {
+        (**self).translate_batch_with_prefixes(source, target_prefixes, options)
+    }
+}
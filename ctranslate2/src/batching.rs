@@ -0,0 +1,479 @@
+//! Dynamic batching of concurrent requests.
+//!
+//! CTranslate2 is much faster on batches than on single sentences, but web handlers usually
+//! translate one sentence each. A [`BatchingTranslator`] collects the sentences submitted by
+//! many threads or tasks into a queue, and a background worker translates them in batches with
+//! [`Translator2::translate_batch`].
+//!
+//! Requests are grouped by compatible [`TranslationOptions`], i.e. options equal except for
+//! `max_batch_size` and `batch_type`. A group is translated as soon as it reaches
+//! [`BatchingConfig::max_batch_size`] sentences, or tokens with [`BatchType::Tokens`], or when
+//! its oldest request has waited [`BatchingConfig::max_wait`].
+//!
+//! Every request returns a [`TranslationHandle`], which either blocks until the translation is
+//! ready with [`TranslationHandle::wait`], or is awaited as a future.
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use std::sync::Arc;
+//! use std::thread;
+//!
+//! use ctranslate2::Translator2;
+//! use ctranslate2::batching::{BatchingConfig, BatchingTranslator};
+//! use ctranslate2::tokenizer::auto;
+//!
+//! # fn main() -> Result<()> {
+//! let path = "/path/to/model";
+//! let t = Translator2::new(path, &Default::default(), auto::load(path)?)?;
+//! let t = Arc::new(BatchingTranslator::new(t, BatchingConfig::default()));
+//!
+//! let workers = (0..8)
+//!     .map(|i| {
+//!         let t = t.clone();
+//!         thread::spawn(move || t.translate(format!("Sentence {i}."), Default::default()).wait())
+//!     })
+//!     .collect::<Vec<_>>();
+//! for w in workers {
+//!     println!("{}", w.join().unwrap()?.text);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+
+use crate::translation::Translation;
+use crate::translator::BatchType;
+use crate::{Tokenizer, TranslationBackend, TranslationOptions, Translator, Translator2};
+
+/// When a [`BatchingTranslator`] translates a group of requests.
+#[derive(Clone, Debug)]
+pub struct BatchingConfig {
+    /// The maximum number of sentences of a batch, or of tokens with [`BatchType::Tokens`].
+    pub max_batch_size: usize,
+    /// Whether `max_batch_size` counts sentences or source tokens.
+    pub batch_type: BatchType,
+    /// The maximum time a request waits for other requests to fill its batch.
+    pub max_wait: Duration,
+}
+
+impl Default for BatchingConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 32,
+            batch_type: BatchType::Examples,
+            max_wait: Duration::from_millis(5),
+        }
+    }
+}
+
+/// The result of a request, set once by the worker.
+#[derive(Default)]
+struct Slot {
+    state: Mutex<SlotState>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct SlotState {
+    result: Option<Result<Translation>>,
+    waker: Option<Waker>,
+    taken: bool,
+}
+
+impl Slot {
+    fn lock(&self) -> MutexGuard<'_, SlotState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sets the result, unless one was already set.
+    fn set(&self, result: Result<Translation>) {
+        let mut state = self.lock();
+        if state.result.is_some() || state.taken {
+            return;
+        }
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.ready.notify_all();
+    }
+
+    fn take(state: &mut SlotState) -> Result<Translation> {
+        state.taken = true;
+        state
+            .result
+            .take()
+            .unwrap_or_else(|| Err(anyhow!("the translation was already taken")))
+    }
+}
+
+/// The pending translation of a request to a [`BatchingTranslator`].
+///
+/// It can be waited for on a thread with [`wait`](Self::wait), or awaited in an async task.
+pub struct TranslationHandle {
+    slot: Arc<Slot>,
+}
+
+impl TranslationHandle {
+    fn ready(result: Result<Translation>) -> Self {
+        let slot = Arc::new(Slot::default());
+        slot.set(result);
+        Self { slot }
+    }
+
+    /// Blocks until the translation is ready and returns it.
+    pub fn wait(self) -> Result<Translation> {
+        let mut state = self.slot.lock();
+        while state.result.is_none() && !state.taken {
+            state = self
+                .slot
+                .ready
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        Slot::take(&mut state)
+    }
+
+    /// Returns whether the translation is ready.
+    pub fn is_ready(&self) -> bool {
+        self.slot.lock().result.is_some()
+    }
+}
+
+impl Future for TranslationHandle {
+    type Output = Result<Translation>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.lock();
+        if state.result.is_some() || state.taken {
+            return Poll::Ready(Slot::take(&mut state));
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+struct Request {
+    source: String,
+    slot: Arc<Slot>,
+}
+
+impl Drop for Request {
+    /// Fails the translation of a request dropped without a result, e.g. when the worker
+    /// stopped, so that its handle does not wait forever.
+    fn drop(&mut self) {
+        self.slot.set(Err(anyhow!(
+            "the request was dropped before being translated"
+        )));
+    }
+}
+
+/// Requests with compatible options, translated in one batch.
+struct Group {
+    key: String,
+    options: TranslationOptions,
+    requests: Vec<Request>,
+    size: usize,
+    since: Instant,
+    full: bool,
+}
+
+#[derive(Default)]
+struct Queue {
+    groups: Vec<Group>,
+    closed: bool,
+}
+
+struct Shared<T: Tokenizer, B: TranslationBackend> {
+    translator: Translator2<T, B>,
+    config: BatchingConfig,
+    queue: Mutex<Queue>,
+    changed: Condvar,
+}
+
+impl<T: Tokenizer, B: TranslationBackend> Shared<T, B> {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Waits for a group to translate. Returns none once closed and empty.
+    fn next_group(&self) -> Option<Group> {
+        let mut queue = self.lock();
+        loop {
+            let now = Instant::now();
+            let due = queue.groups.iter().position(|g| g.full).or_else(|| {
+                queue
+                    .groups
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, g)| queue.closed || g.since + self.config.max_wait <= now)
+                    .min_by_key(|(_, g)| g.since)
+                    .map(|(i, _)| i)
+            });
+            if let Some(idx) = due {
+                return Some(queue.groups.remove(idx));
+            }
+            if queue.closed {
+                return None;
+            }
+            queue = match queue.groups.iter().map(|g| g.since).min() {
+                Some(since) => {
+                    let timeout = (since + self.config.max_wait).saturating_duration_since(now);
+                    self.changed
+                        .wait_timeout(queue, timeout)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0
+                }
+                None => self.changed.wait(queue).unwrap_or_else(|e| e.into_inner()),
+            };
+        }
+    }
+
+    fn run(&self) {
+        let _stop = Stop(self);
+        while let Some(group) = self.next_group() {
+            let sources = group
+                .requests
+                .iter()
+                .map(|r| r.source.clone())
+                .collect::<Vec<_>>();
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.translator.translate_batch(&sources, group.options)
+            }));
+            match res {
+                Ok(Ok(translations)) => {
+                    for (request, translation) in group.requests.iter().zip(translations) {
+                        request.slot.set(Ok(translation));
+                    }
+                }
+                Ok(Err(err)) => {
+                    for request in &group.requests {
+                        request.slot.set(Err(anyhow!("{err:#}")));
+                    }
+                }
+                Err(payload) => {
+                    let msg = payload
+                        .downcast_ref::<&str>()
+                        .copied()
+                        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                        .unwrap_or("unknown panic");
+                    for request in &group.requests {
+                        request
+                            .slot
+                            .set(Err(anyhow!("the translation panicked: {msg}")));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Closes the queue when the worker stops, even by panicking: later requests are rejected and
+/// the queued ones fail.
+struct Stop<'a, T: Tokenizer, B: TranslationBackend>(&'a Shared<T, B>);
+
+impl<T: Tokenizer, B: TranslationBackend> Drop for Stop<'_, T, B> {
+    fn drop(&mut self) {
+        let groups = {
+            let mut queue = self.0.lock();
+            queue.closed = true;
+            std::mem::take(&mut queue.groups)
+        };
+        drop(groups);
+    }
+}
+
+/// A translator batching the requests of concurrent callers.
+///
+/// Dropping it translates the pending requests and stops its worker thread.
+pub struct BatchingTranslator<T: Tokenizer + 'static, B: TranslationBackend + 'static = Translator>
+{
+    shared: Arc<Shared<T, B>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<T: Tokenizer + 'static, B: TranslationBackend + 'static> BatchingTranslator<T, B> {
+    /// Starts a worker translating the requests with `translator`.
+    pub fn new(translator: Translator2<T, B>, config: BatchingConfig) -> Self {
+        let shared = Arc::new(Shared {
+            translator,
+            config,
+            queue: Mutex::new(Queue::default()),
+            changed: Condvar::new(),
+        });
+        let worker = {
+            let shared = shared.clone();
+            thread::spawn(move || shared.run())
+        };
+        Self {
+            shared,
+            worker: Some(worker),
+        }
+    }
+
+    /// Returns the translator.
+    pub fn translator(&self) -> &Translator2<T, B> {
+        &self.shared.translator
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &BatchingConfig {
+        &self.shared.config
+    }
+
+    /// Queues a sentence and returns the handle of its translation.
+    pub fn translate<S: Into<String>>(
+        &self,
+        source: S,
+        options: TranslationOptions,
+    ) -> TranslationHandle {
+        let source = source.into();
+        let config = &self.shared.config;
+        let size = match config.batch_type {
+            BatchType::Examples => 1,
+            BatchType::Tokens => match self.shared.translator.tokenizer().encode(&source) {
+                Ok(tokens) => tokens.len(),
+                Err(err) => return TranslationHandle::ready(Err(err)),
+            },
+        };
+        let key = options.decoding_key();
+        let slot = Arc::new(Slot::default());
+
+        let mut queue = self.shared.lock();
+        if queue.closed {
+            return TranslationHandle::ready(Err(anyhow!("the batching translator is stopped")));
+        }
+        let idx = match queue.groups.iter().position(|g| g.key == key && !g.full) {
+            Some(idx) if queue.groups[idx].size + size > config.max_batch_size => {
+                queue.groups[idx].full = true;
+                None
+            }
+            idx => idx,
+        };
+        let idx = idx.unwrap_or_else(|| {
+            queue.groups.push(Group {
+                key,
+                options,
+                requests: Vec::new(),
+                size: 0,
+                since: Instant::now(),
+                full: false,
+            });
+            queue.groups.len() - 1
+        });
+        let group = &mut queue.groups[idx];
+        group.requests.push(Request {
+            source,
+            slot: slot.clone(),
+        });
+        group.size += size;
+        group.full = group.size >= config.max_batch_size;
+        drop(queue);
+        self.shared.changed.notify_all();
+        TranslationHandle { slot }
+    }
+}
+
+impl<T: Tokenizer + 'static, B: TranslationBackend + 'static> Drop for BatchingTranslator<T, B> {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[test]
+fn batch_requests() {
+    use crate::backend::mock::MockBackend;
+    use crate::tokenizer::whitespace;
+
+    let translator =
+        || Translator2::with_backend(MockBackend::echo(), whitespace::Tokenizer::new());
+    let t = BatchingTranslator::new(
+        translator(),
+        BatchingConfig {
+            max_batch_size: 3,
+            max_wait: Duration::from_secs(60),
+            ..Default::default()
+        },
+    );
+    let handles = ["a", "b", "c"].map(|s| t.translate(s, Default::default()));
+    let texts = handles.map(|h| h.wait().unwrap().text);
+    assert_eq!(texts, ["a", "b", "c"]);
+    assert_eq!(t.translator().backend().calls(), 1);
+
+    let t = BatchingTranslator::new(
+        translator(),
+        BatchingConfig {
+            max_batch_size: 4,
+            batch_type: BatchType::Tokens,
+            max_wait: Duration::from_millis(10),
+        },
+    );
+    let beam = TranslationOptions {
+        beam_size: 4,
+        ..Default::default()
+    };
+    let handles = [
+        t.translate("a b", Default::default()),
+        t.translate("c d", Default::default()),
+        t.translate("e", beam),
+    ];
+    for h in handles {
+        assert!(h.wait().is_ok());
+    }
+    assert_eq!(
+        t.translator().backend().batches(),
+        [
+            vec![vec!["a", "b", "</s>"]],
+            vec![vec!["c", "d", "</s>"]],
+            vec![vec!["e", "</s>"]]
+        ]
+    );
+
+    let pending = t.translate("f", Default::default());
+    drop(t);
+    assert_eq!(pending.wait().unwrap().text, "f");
+
+    struct Panicking;
+
+    impl crate::processor::Processor for Panicking {
+        fn preprocess(
+            &self,
+            text: String,
+            _metadata: &mut crate::processor::Metadata,
+        ) -> Result<String> {
+            assert_ne!(text, "boom", "cannot preprocess");
+            Ok(text)
+        }
+    }
+
+    let mut translator = translator();
+    translator.with_processor(Panicking);
+    let t = BatchingTranslator::new(
+        translator,
+        BatchingConfig {
+            max_batch_size: 2,
+            max_wait: Duration::from_secs(60),
+            ..Default::default()
+        },
+    );
+    let handles = ["a", "boom"].map(|s| t.translate(s, Default::default()));
+    for h in handles {
+        let err = h.wait().unwrap_err().to_string();
+        assert!(err.starts_with("the translation panicked"), "{err}");
+    }
+    let handles = ["a", "b"].map(|s| t.translate(s, Default::default()));
+    assert_eq!(handles.map(|h| h.wait().unwrap().text), ["a", "b"]);
+}
@@ -0,0 +1,343 @@
+//! Caching of translations.
+//!
+//! Traffic often repeats the same sources, e.g. UI strings and boilerplate sentences. A
+//! [`Cache`] attached with [`Translator2::with_cache`](crate::Translator2::with_cache) keeps
+//! the translations returned by
+//! [`translate_batch`](crate::Translator2::translate_batch): the sources of a batch found in
+//! the cache are served from it, and only the others are translated.
+//!
+//! A translation is stored under a key made of the NFC-normalized source, the model id given
+//! to [`Cache::new`], a fingerprint of the translator configuration and the
+//! [`TranslationOptions`] fields that affect the output. The fingerprint covers the tokenizer
+//! type, the processors (see [`Processor::id`](crate::processor::Processor::id)), the overflow
+//! policy, the masker and the glossary, so differently configured translators can share a
+//! store. The tokenizer files and settings are not part of it, nor the tokenizer type behind a
+//! `Box<dyn Tokenizer>`: the model id must identify them along with the model. Translations
+//! with sampling (`sampling_topk` other than 1) are not cached.
+//!
+//! The fingerprint is made of type names and `Debug` output, which can change with the
+//! compiler and the crate versions. It is only meant to be stable within a build: a
+//! persistent store should be cleared, or the model id changed, when the application is
+//! rebuilt.
+//!
+//! Translations are kept in a [`CacheStore`]. [`LruStore`] keeps them in memory, with a
+//! maximum number of entries and an optional time to live; other stores, e.g. backed by a
+//! local database, implement the trait. A store wrapped in an [`Arc`] can be shared by several
+//! caches. With the `serde` feature, [`Translation`] implements `Serialize` and `Deserialize`
+//! for such stores.
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use std::time::Duration;
+//!
+//! use ctranslate2::Translator2;
+//! use ctranslate2::cache::{Cache, LruStore};
+//! use ctranslate2::tokenizer::auto;
+//!
+//! # fn main() -> Result<()> {
+//! let path = "/path/to/model";
+//! let mut store = LruStore::new(10_000);
+//! store.with_ttl(Duration::from_secs(3600));
+//!
+//! let mut t = Translator2::new(path, &Default::default(), auto::load(path)?)?;
+//! t.with_cache(Cache::new("opus-mt-en-de", store));
+//! t.translate_batch(&["Save".to_owned(), "Cancel".to_owned()], Default::default())?;
+//! println!("{:?}", t.cache().map(|c| c.stats()));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::TranslationOptions;
+use crate::translation::Translation;
+
+/// A storage of translations by key.
+pub trait CacheStore: Send + Sync {
+    /// Returns the translation stored under a key.
+    fn get(&self, key: &str) -> Result<Option<Translation>>;
+
+    /// Stores a translation under a key, replacing any previous one.
+    fn insert(&self, key: String, translation: Translation) -> Result<()>;
+
+    /// Removes all the translations.
+    fn clear(&self) -> Result<()>;
+}
+
+impl<S: CacheStore + ?Sized> CacheStore for Arc<S> {
+    fn get(&self, key: &str) -> Result<Option<Translation>> {
+        (**self).get(key)
+    }
+
+    fn insert(&self, key: String, translation: Translation) -> Result<()> {
+        (**self).insert(key, translation)
+    }
+
+    fn clear(&self) -> Result<()> {
+        (**self).clear()
+    }
+}
+
+/// The number of sources served from the cache and translated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Returns the ratio of sources served from the cache, or zero if there was none.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// A cache of translations, with hit and miss counters.
+pub struct Cache {
+    model_id: String,
+    store: Box<dyn CacheStore>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Cache {
+    /// Creates a cache of the translations of the given model, kept in `store`. The model id
+    /// must identify the model and the files and settings of its tokenizer.
+    pub fn new<M: Into<String>, S: CacheStore + 'static>(model_id: M, store: S) -> Self {
+        Self {
+            model_id: model_id.into(),
+            store: Box::new(store),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Creates a cache keeping up to `capacity` translations in memory.
+    pub fn in_memory<M: Into<String>>(model_id: M, capacity: usize) -> Self {
+        Self::new(model_id, LruStore::new(capacity))
+    }
+
+    /// Returns the model id.
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// Returns the number of hits and misses since the cache was created or the counters
+    /// were reset.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resets the hit and miss counters.
+    pub fn reset_stats(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    /// Removes all the translations from the store.
+    pub fn clear(&self) -> Result<()> {
+        self.store.clear()
+    }
+
+    /// Returns the key of the translation of a source by a translator with the given
+    /// configuration fingerprint.
+    pub(crate) fn key(&self, config: &str, options: &TranslationOptions, source: &str) -> String {
+        format!(
+            "{}\u{1f}{}\u{1f}{}\u{1f}{}",
+            self.model_id,
+            config,
+            options.decoding_key(),
+            source.nfc().collect::<String>()
+        )
+    }
+
+    /// Returns the translation stored under a key, counting a hit or a miss. The elapsed time
+    /// of a hit is zero.
+    pub(crate) fn get(&self, key: &str) -> Result<Option<Translation>> {
+        let res = self.store.get(key)?;
+        let counter = if res.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        Ok(res.map(|t| Translation {
+            elapsed: Duration::ZERO,
+            ..t
+        }))
+    }
+
+    pub(crate) fn insert(&self, key: String, translation: Translation) -> Result<()> {
+        self.store.insert(key, translation)
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of a text in hexadecimal.
+pub(crate) fn fingerprint(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf29ce484222325_u64, |h, b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{hash:016x}")
+}
+
+struct Entry {
+    translation: Translation,
+    inserted: Instant,
+    /// The time of the last use, in number of uses of the store.
+    used: u64,
+}
+
+#[derive(Default)]
+struct Lru {
+    entries: HashMap<String, Entry>,
+    /// The keys by time of last use.
+    order: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+/// An in-memory [`CacheStore`] evicting the least recently used translations.
+pub struct LruStore {
+    capacity: usize,
+    ttl: Option<Duration>,
+    lru: Mutex<Lru>,
+}
+
+impl LruStore {
+    /// Creates a store keeping up to `capacity` translations.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ttl: None,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// Sets how long a translation is kept after it is stored.
+    pub fn with_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Returns the number of stored translations, including expired ones not removed yet.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Returns whether no translation is stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.lru.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CacheStore for LruStore {
+    fn get(&self, key: &str) -> Result<Option<Translation>> {
+        let mut lru = self.lock();
+        let Lru {
+            entries,
+            order,
+            clock,
+        } = &mut *lru;
+        let Some(entry) = entries.get_mut(key) else {
+            return Ok(None);
+        };
+        order.remove(&entry.used);
+        if self.ttl.is_some_and(|ttl| entry.inserted.elapsed() > ttl) {
+            entries.remove(key);
+            return Ok(None);
+        }
+        *clock += 1;
+        entry.used = *clock;
+        order.insert(*clock, key.to_owned());
+        Ok(Some(entry.translation.clone()))
+    }
+
+    fn insert(&self, key: String, translation: Translation) -> Result<()> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        let mut lru = self.lock();
+        lru.clock += 1;
+        let used = lru.clock;
+        if let Some(old) = lru.entries.remove(&key) {
+            lru.order.remove(&old.used);
+        }
+        while lru.entries.len() >= self.capacity {
+            let Some((_, oldest)) = lru.order.pop_first() else {
+                break;
+            };
+            lru.entries.remove(&oldest);
+        }
+        lru.order.insert(used, key.clone());
+        lru.entries.insert(
+            key,
+            Entry {
+                translation,
+                inserted: Instant::now(),
+                used,
+            },
+        );
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<()> {
+        let mut lru = self.lock();
+        lru.entries.clear();
+        lru.order.clear();
+        Ok(())
+    }
+}
+
+#[test]
+fn lru_store() {
+    let translation = |text: &str| Translation {
+        text: text.to_owned(),
+        ..Default::default()
+    };
+    let store = LruStore::new(2);
+    store.insert("a".to_owned(), translation("A")).unwrap();
+    store.insert("b".to_owned(), translation("B")).unwrap();
+    assert_eq!(
+        store.get("a").unwrap().map(|t| t.text),
+        Some("A".to_owned())
+    );
+    store.insert("c".to_owned(), translation("C")).unwrap();
+    assert!(store.get("b").unwrap().is_none());
+    assert!(store.get("a").unwrap().is_some());
+    assert_eq!(store.len(), 2);
+
+    let mut store = LruStore::new(2);
+    store.with_ttl(Duration::ZERO);
+    store.insert("a".to_owned(), translation("A")).unwrap();
+    std::thread::sleep(Duration::from_millis(1));
+    assert!(store.get("a").unwrap().is_none());
+    assert!(store.is_empty());
+
+    let cache = Cache::in_memory("model", 10);
+    let options = TranslationOptions::default();
+    let key = cache.key("config", &options, "Cafe\u{301}");
+    assert_eq!(key, cache.key("config", &options, "Café"));
+    assert!(cache.get(&key).unwrap().is_none());
+    cache.insert(key.clone(), translation("Coffee")).unwrap();
+    assert!(cache.get(&key).unwrap().is_some());
+    assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 1 });
+    assert_eq!(cache.stats().hit_rate(), 0.5);
+
+    assert_eq!(fingerprint(""), "cbf29ce484222325");
+    assert_ne!(fingerprint("a"), fingerprint("b"));
+}
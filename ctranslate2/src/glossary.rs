@@ -0,0 +1,620 @@
+//! Glossaries of fixed translations.
+//!
+//! A [`Glossary`] lists the required translations of product names and domain terms. Attached
+//! to a translator with [`Translator2::with_glossary`](crate::Translator2::with_glossary), it
+//! finds the terms in every source, steers the translation towards their target terms as set
+//! by [`Steering`], and checks that the final translation contains them. The result of the
+//! check is returned in [`Translation::glossary_report`](crate::Translation::glossary_report).
+//!
+//! Terms are matched case-insensitively, and a word of a term also matches a word differing
+//! only by a short ending, so `server` finds `Servers` and `library` finds `libraries`.
+//! Glossaries are loaded from CSV files with [`Glossary::from_csv`] and from TBX files with
+//! [`Glossary::from_tbx`].
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use ctranslate2::Translator2;
+//! use ctranslate2::glossary::{Glossary, Steering};
+//! use ctranslate2::tokenizer::auto;
+//!
+//! # fn main() -> Result<()> {
+//! let path = "/path/to/model";
+//! let mut glossary = Glossary::from_tbx("/path/to/terms.tbx", "en", "de")?;
+//! glossary.with_term("Acme Cloud", "Acme Cloud").with_steering(Steering::Placeholder);
+//!
+//! let mut t = Translator2::new(path, &Default::default(), auto::load(path)?)?;
+//! t.with_glossary(glossary)?;
+//! let sources = ["Restart the Acme Cloud server.".to_owned()];
+//! for r in t.translate_batch(&sources, Default::default())? {
+//!     let report = r.glossary_report.unwrap_or_default();
+//!     for term in report.missing() {
+//!         println!("{} was not translated as {}", term.source, term.target);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::processor::CjkSpacing;
+use crate::xml::{Event, Events, attr};
+
+const DEFAULT_MAX_SUFFIX: usize = 3;
+/// The minimum number of leading characters an inflected word shares with the term word.
+const MIN_STEM: usize = 4;
+
+/// An error loading a glossary.
+#[derive(Debug)]
+pub enum GlossaryError {
+    /// The glossary file does not exist.
+    FileNotFound(PathBuf),
+    /// The glossary file exists but could not be loaded.
+    Parse { path: PathBuf, message: String },
+}
+
+impl GlossaryError {
+    fn parse<E: fmt::Display>(path: &Path, err: E) -> GlossaryError {
+        GlossaryError::Parse {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for GlossaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GlossaryError::FileNotFound(path) => {
+                write!(f, "Glossary file not found: {}", path.display())
+            }
+            GlossaryError::Parse { path, message } => {
+                write!(f, "Failed to load glossary {}: {}", path.display(), message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GlossaryError {}
+
+/// How a [`Glossary`] steers the translation towards its target terms.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Steering {
+    /// Replaces the source terms with placeholders such as `｟T0｠`, and the placeholders in the
+    /// translation with the target terms. Requires a model copying the placeholders, like the
+    /// sentinels of [`mask`](crate::mask).
+    #[default]
+    Placeholder,
+    /// Forces the target term as target prefix when a source starts with a term, in
+    /// [`Translator2::translate_batch`](crate::Translator2::translate_batch). The other terms
+    /// are only checked.
+    Prefix,
+    /// Only checks the translations.
+    None,
+}
+
+/// A source term and its required translation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Term {
+    pub source: String,
+    pub target: String,
+}
+
+/// An occurrence of a term in a text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TermMatch {
+    /// The index of the term in the glossary.
+    pub term: usize,
+    /// The byte offset of the start of the occurrence.
+    pub start: usize,
+    /// The byte offset of the end of the occurrence.
+    pub end: usize,
+}
+
+/// Whether the translation of a source contains the target term of a source term.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TermCheck {
+    pub source: String,
+    pub target: String,
+    pub found: bool,
+}
+
+/// The check of the terms found in a source against its translation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GlossaryReport {
+    /// The terms found in the source, in order of first occurrence.
+    pub terms: Vec<TermCheck>,
+}
+
+impl GlossaryReport {
+    /// Returns whether every target term was found in the translation.
+    pub fn is_ok(&self) -> bool {
+        self.terms.iter().all(|t| t.found)
+    }
+
+    /// Returns the terms whose target term is missing from the translation.
+    pub fn missing(&self) -> impl Iterator<Item = &TermCheck> {
+        self.terms.iter().filter(|t| !t.found)
+    }
+}
+
+/// A list of terms with fixed translations.
+#[derive(Clone, Debug)]
+pub struct Glossary {
+    terms: Vec<Term>,
+    /// The words of the source and target terms.
+    words: Vec<(Vec<String>, Vec<String>)>,
+    steering: Steering,
+    max_suffix: usize,
+    placeholder: Regex,
+}
+
+impl Default for Glossary {
+    fn default() -> Self {
+        Self {
+            terms: Vec::new(),
+            words: Vec::new(),
+            steering: Steering::default(),
+            max_suffix: DEFAULT_MAX_SUFFIX,
+            placeholder: Regex::new(r"｟\s*T\s*([0-9]+)\s*｠")
+                .unwrap_or_else(|err| unreachable!("{err}")),
+        }
+    }
+}
+
+impl Glossary {
+    /// Creates an empty glossary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a glossary from a CSV file.
+    ///
+    /// The first two columns are the source and target terms; further columns are ignored.
+    /// Columns are separated by commas, or by tabs if the first line contains one, and may be
+    /// quoted with `"`. A first row `source,target` is a header and skipped, as are rows with
+    /// an empty term.
+    pub fn from_csv<P: AsRef<Path>>(path: P) -> Result<Self, GlossaryError> {
+        let path = path.as_ref();
+        let content = read(path)?;
+        let mut res = Self::new();
+        for (source, target) in
+            parse_csv(&content).map_err(|err| GlossaryError::parse(path, err))?
+        {
+            res.with_term(source, target);
+        }
+        Ok(res)
+    }
+
+    /// Loads the terms of a TBX file translating from `src_lang` into `tgt_lang`, e.g. `en` and
+    /// `de`. A language without a region matches the language sets of all its regions.
+    ///
+    /// Every source term of an entry is mapped to the first target term of the entry.
+    pub fn from_tbx<P: AsRef<Path>>(
+        path: P,
+        src_lang: &str,
+        tgt_lang: &str,
+    ) -> Result<Self, GlossaryError> {
+        let path = path.as_ref();
+        let content = read(path)?;
+        let terms = parse_tbx(&content, src_lang, tgt_lang)
+            .map_err(|err| GlossaryError::parse(path, err))?;
+        if terms.is_empty() {
+            return Err(GlossaryError::parse(
+                path,
+                format!("no terms from {src_lang} into {tgt_lang}"),
+            ));
+        }
+        let mut res = Self::new();
+        for (source, target) in terms {
+            res.with_term(source, target);
+        }
+        Ok(res)
+    }
+
+    /// Adds a term. When terms overlap in a source, the one with the most words is used.
+    pub fn with_term<S: Into<String>, T: Into<String>>(
+        &mut self,
+        source: S,
+        target: T,
+    ) -> &mut Self {
+        let term = Term {
+            source: source.into(),
+            target: target.into(),
+        };
+        let words = |text: &str| {
+            words(text)
+                .into_iter()
+                .map(|(s, e)| text[s..e].to_owned())
+                .collect()
+        };
+        self.words.push((words(&term.source), words(&term.target)));
+        self.terms.push(term);
+        self
+    }
+
+    /// Sets how the translation is steered towards the target terms.
+    pub fn with_steering(&mut self, steering: Steering) -> &mut Self {
+        self.steering = steering;
+        self
+    }
+
+    /// Sets the maximum number of characters by which the ending of a word may differ from the
+    /// word of a term, 3 by default. Words of less than 4 characters must always match
+    /// exactly, as must all words if it is zero.
+    pub fn with_max_suffix(&mut self, max_suffix: usize) -> &mut Self {
+        self.max_suffix = max_suffix;
+        self
+    }
+
+    /// Returns how the translation is steered towards the target terms.
+    pub fn steering(&self) -> Steering {
+        self.steering
+    }
+
+    /// Returns the placeholder of the term with the given index, used with
+    /// [`Steering::Placeholder`].
+    pub fn placeholder(&self, term: usize) -> String {
+        format!("｟T{term}｠")
+    }
+
+    /// Returns whether a text contains the placeholder of a term, possibly with spaces added
+    /// by the tokenizer.
+    pub(crate) fn has_placeholder(&self, text: &str, term: usize) -> bool {
+        self.placeholder
+            .captures_iter(text)
+            .any(|c| c[1].parse() == Ok(term))
+    }
+
+    /// Returns the terms.
+    pub fn terms(&self) -> &[Term] {
+        &self.terms
+    }
+
+    /// Returns the number of terms.
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// Returns whether the glossary has no term.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Returns the non-overlapping occurrences of the source terms in a text.
+    pub fn find(&self, text: &str) -> Vec<TermMatch> {
+        let spans = words(text);
+        let mut res = Vec::new();
+        let mut i = 0;
+        while i < spans.len() {
+            let best = self
+                .words
+                .iter()
+                .enumerate()
+                .filter(|(_, (source, _))| self.matches_at(text, &spans[i..], source))
+                .max_by_key(|(idx, (source, _))| (source.len(), self.terms[*idx].source.len()));
+            match best {
+                Some((term, (source, _))) => {
+                    res.push(TermMatch {
+                        term,
+                        start: spans[i].0,
+                        end: spans[i + source.len() - 1].1,
+                    });
+                    i += source.len();
+                }
+                None => i += 1,
+            }
+        }
+        res
+    }
+
+    /// Checks that a translation contains the target terms of the given occurrences.
+    pub fn check(&self, matches: &[TermMatch], translation: &str) -> GlossaryReport {
+        let spans = words(translation);
+        let mut seen = Vec::new();
+        let mut res = GlossaryReport::default();
+        for m in matches {
+            if seen.contains(&m.term) {
+                continue;
+            }
+            seen.push(m.term);
+            let target = &self.words[m.term].1;
+            let found = (0..spans.len()).any(|i| self.matches_at(translation, &spans[i..], target));
+            res.terms.push(TermCheck {
+                source: self.terms[m.term].source.clone(),
+                target: self.terms[m.term].target.clone(),
+                found,
+            });
+        }
+        res
+    }
+
+    /// Replaces the occurrences of the terms in a text with placeholders.
+    pub(crate) fn substitute(&self, text: &str, matches: &[TermMatch]) -> String {
+        let mut res = String::with_capacity(text.len());
+        let mut last = 0;
+        for m in matches {
+            res.push_str(&text[last..m.start]);
+            res.push_str(&self.placeholder(m.term));
+            last = m.end;
+        }
+        res.push_str(&text[last..]);
+        res
+    }
+
+    /// Replaces the placeholders in a translation with the target terms.
+    pub(crate) fn restore(&self, text: &str) -> String {
+        self.placeholder
+            .replace_all(text, |c: &regex::Captures| {
+                match c[1]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|idx| self.terms.get(idx))
+                {
+                    Some(term) => term.target.clone(),
+                    None => c[0].to_owned(),
+                }
+            })
+            .into_owned()
+    }
+
+    /// Returns whether the words of a term match the words at the start of `spans`.
+    fn matches_at(&self, text: &str, spans: &[(usize, usize)], term: &[String]) -> bool {
+        !term.is_empty()
+            && spans.len() >= term.len()
+            && term
+                .iter()
+                .zip(spans)
+                .all(|(t, &(s, e))| self.word_matches(&text[s..e], t))
+    }
+
+    fn word_matches(&self, word: &str, term: &str) -> bool {
+        let (word, term) = (word.to_lowercase(), term.to_lowercase());
+        if word == term {
+            return true;
+        }
+        let (word, term) = (
+            word.chars().collect::<Vec<_>>(),
+            term.chars().collect::<Vec<_>>(),
+        );
+        let common = word.iter().zip(&term).take_while(|(w, t)| w == t).count();
+        self.max_suffix > 0
+            && common >= MIN_STEM
+            && word[..common].iter().all(|c| c.is_alphabetic())
+            && word.len() - common <= self.max_suffix
+            && term.len() - common <= self.max_suffix
+    }
+}
+
+fn read(path: &Path) -> Result<String, GlossaryError> {
+    if !path.is_file() {
+        return Err(GlossaryError::FileNotFound(path.to_path_buf()));
+    }
+    fs::read_to_string(path).map_err(|err| GlossaryError::parse(path, err))
+}
+
+/// Returns the byte spans of the words of a text: runs of letters and digits, and single
+/// CJK characters and punctuation marks.
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let single = |c: char| CjkSpacing::is_cjk(c) && !CjkSpacing::is_hangul(c);
+    let mut res: Vec<(usize, usize)> = Vec::new();
+    let mut in_word = false;
+    for (idx, c) in text.char_indices() {
+        let end = idx + c.len_utf8();
+        if c.is_whitespace() {
+            in_word = false;
+        } else if c.is_alphanumeric() && !single(c) {
+            match res.last_mut() {
+                Some(last) if in_word => last.1 = end,
+                _ => res.push((idx, end)),
+            }
+            in_word = true;
+        } else {
+            res.push((idx, end));
+            in_word = false;
+        }
+    }
+    res
+}
+
+/// Returns the source and target terms of a CSV document.
+fn parse_csv(content: &str) -> Result<Vec<(String, String)>, String> {
+    let first_line = content.lines().next().unwrap_or_default();
+    let delimiter = if first_line.contains('\t') { '\t' } else { ',' };
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut chars = content.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if cell.trim().is_empty() => {
+                cell.clear();
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            cell.push('"');
+                        }
+                        Some('"') => break,
+                        Some(c) => {
+                            line += usize::from(c == '\n');
+                            cell.push(c);
+                        }
+                        None => return Err(format!("unterminated quote on line {line}")),
+                    }
+                }
+            }
+            '\n' => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+                line += 1;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            c if c == delimiter => row.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+
+    let mut res = Vec::new();
+    for (idx, row) in rows.into_iter().enumerate() {
+        let [source, target, ..] = row.as_slice() else {
+            continue;
+        };
+        let (source, target) = (source.trim(), target.trim());
+        let header = idx == 0
+            && source.eq_ignore_ascii_case("source")
+            && target.eq_ignore_ascii_case("target");
+        if !header && !source.is_empty() && !target.is_empty() {
+            res.push((source.to_owned(), target.to_owned()));
+        }
+    }
+    Ok(res)
+}
+
+/// Returns whether the language tag of a TBX language set matches a requested language.
+fn lang_matches(tag: &str, lang: &str) -> bool {
+    tag.eq_ignore_ascii_case(lang)
+        || (!lang.contains(['-', '_'])
+            && tag
+                .split(['-', '_'])
+                .next()
+                .is_some_and(|primary| primary.eq_ignore_ascii_case(lang)))
+}
+
+/// Returns the source and target terms of a TBX document. Both the `termEntry` and
+/// `langSet` elements of TBX 2 and the `conceptEntry` and `langSec` elements of TBX 3 are read.
+fn parse_tbx(
+    content: &str,
+    src_lang: &str,
+    tgt_lang: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let mut res = Vec::new();
+    let (mut sources, mut targets) = (Vec::new(), Vec::new());
+    let mut lang = None;
+    let mut term = None::<String>;
+
+    for event in Events::new(content) {
+        match event? {
+            Event::Start(name, attrs) => match name {
+                "termEntry" | "conceptEntry" => {
+                    sources.clear();
+                    targets.clear();
+                }
+                "langSet" | "langSec" => lang = attr(&attrs, "lang").map(str::to_owned),
+                "term" => term = Some(String::new()),
+                _ => {}
+            },
+            Event::Text(t) => {
+                if let Some(term) = &mut term {
+                    term.push_str(&t);
+                }
+            }
+            Event::End(name) => match name {
+                "term" => {
+                    let (Some(text), Some(lang)) = (term.take(), &lang) else {
+                        continue;
+                    };
+                    let text = text.trim().to_owned();
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if lang_matches(lang, src_lang) {
+                        sources.push(text);
+                    } else if lang_matches(lang, tgt_lang) {
+                        targets.push(text);
+                    }
+                }
+                "langSet" | "langSec" => lang = None,
+                "termEntry" | "conceptEntry" => {
+                    if let Some(target) = targets.first() {
+                        res.extend(sources.drain(..).map(|s| (s, target.clone())));
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+    Ok(res)
+}
+
+#[test]
+fn glossary_terms() {
+    let mut g = Glossary::new();
+    g.with_term("server", "Server")
+        .with_term("cloud server", "Cloud-Server")
+        .with_term("library", "Bibliothek")
+        .with_term("C++", "C++");
+    let text = "Two Cloud Servers, one server and C++ libraries.";
+    let found = g
+        .find(text)
+        .into_iter()
+        .map(|m| (g.terms()[m.term].target.as_str(), &text[m.start..m.end]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        found,
+        [
+            ("Cloud-Server", "Cloud Servers"),
+            ("Server", "server"),
+            ("C++", "C++"),
+            ("Bibliothek", "libraries")
+        ]
+    );
+    assert!(g.find("A serial port").is_empty());
+
+    let matches = g.find(text);
+    assert_eq!(
+        g.substitute(text, &matches),
+        "Two ｟T1｠, one ｟T0｠ and ｟T3｠ ｟T2｠."
+    );
+    assert_eq!(
+        g.restore("Zwei ｟ T1 ｠ und ｟T9｠"),
+        "Zwei Cloud-Server und ｟T9｠"
+    );
+
+    let report = g.check(
+        &matches,
+        "Zwei Cloud-Server, ein Server und C++-Büchereien.",
+    );
+    assert_eq!(
+        report
+            .missing()
+            .map(|t| t.target.as_str())
+            .collect::<Vec<_>>(),
+        ["Bibliothek"]
+    );
+
+    let csv = "source,target\n\"Acme, Inc.\",\"Acme \"\"AG\"\"\"\r\nfoo,\n";
+    assert_eq!(
+        parse_csv(csv).unwrap(),
+        [("Acme, Inc.".to_owned(), "Acme \"AG\"".to_owned())]
+    );
+
+    let tbx = r#"<?xml version="1.0"?>
+<martif type="TBX" xml:lang="en"><text><body>
+  <termEntry id="1">
+    <langSet xml:lang="en-US"><tig><term>hard drive</term></tig><tig><term>hard disk</term></tig></langSet>
+    <langSet xml:lang="de"><tig><term>Festplatte</term></tig></langSet>
+  </termEntry>
+  <termEntry id="2"><langSet xml:lang="en"><tig><term>mouse</term></tig></langSet></termEntry>
+</body></text></martif>"#;
+    assert_eq!(
+        parse_tbx(tbx, "en", "de").unwrap(),
+        [
+            ("hard drive".to_owned(), "Festplatte".to_owned()),
+            ("hard disk".to_owned(), "Festplatte".to_owned())
+        ]
+    );
+    assert!(parse_tbx(tbx, "en-GB", "de").unwrap().is_empty());
+}
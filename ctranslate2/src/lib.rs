@@ -1,26 +1,48 @@
 //! # Basic Usage
-//! ```
-//!  let sources = vec![
-//!     "Hallo World!",
-//!     "This crate provides Rust bindings for CTranslate2."
+//! ```no_run
+//! # use anyhow::Result;
+//! use ctranslate2::Translator2;
+//! use ctranslate2::tokenizer::rust_tokenizers::SentenceTokenizer;
+//!
+//! # fn main() -> Result<()> {
+//! let sources = vec![
+//!     "Hallo World!".to_owned(),
+//!     "This crate provides Rust bindings for CTranslate2.".to_owned(),
 //! ];
-//! let translator = Translator2::new("/path/to/model", &Default::default(), tokenizer::rust_tokenizers::SentenceTokenizer::new("/path/to/tokenizer"))?;
-//! let results = translator.translate_batch(&sources, &Default::default())?;
-//! for (r, _) in results{
-//!     println!("{}", r);
+//! let tokenizer = SentenceTokenizer::new("/path/to/tokenizer")?;
+//! let translator = Translator2::new("/path/to/model", &Default::default(), tokenizer)?;
+//! let results = translator.translate_batch(&sources, Default::default())?;
+//! for r in results {
+//!     println!("{}", r.text);
 //! }
+//! # Ok(())
+//! # }
 //! ```
 
-//!
+pub mod alignment;
+pub mod backend;
+pub mod batching;
+pub mod cache;
 pub mod compute_type;
 pub mod device;
+pub mod glossary;
+pub mod mask;
+pub mod multilingual;
+pub mod overflow;
+pub mod processor;
+pub mod segment;
 pub mod tokenizer;
+pub mod translation;
 pub mod translator;
 pub mod translator2;
+mod xml;
+pub use backend::TranslationBackend;
 pub use compute_type::ComputeType;
 pub use device::Device;
+pub use overflow::OverflowPolicy;
 pub use tokenizer::Tokenizer;
+pub use translation::Translation;
 pub use translator::TranslationOptions;
 pub use translator::Translator;
 pub use translator::TranslatorConfig;
-pub use translator2::Translator2;
+pub use translator2::{PrefixOutput, Translator2};
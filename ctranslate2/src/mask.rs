@@ -0,0 +1,254 @@
+//! Masking of markup, placeholders and URLs.
+//!
+//! Models tend to translate or mangle inline tags such as `<b>`, placeholders such as `{name}`
+//! or `%s`, and URLs. A [`Masker`] replaces them with numbered sentinels, `｟0｠`, `｟1｠`, ...,
+//! before the source is encoded, and puts them back into the translation after it is decoded.
+//! [`Translator2::with_masker`](crate::Translator2::with_masker) applies it to every source,
+//! after checking that the tokenizer preserves the sentinels: most SentencePiece vocabularies
+//! lack `｟` and `｠`, so set sentinels known to the model with [`Masker::with_sentinels`].
+//!
+//! The model may drop or repeat a sentinel. Every translation reports it in a [`MaskReport`].
+//! With [`Masker::with_reinsertion`], a dropped sentinel is inserted again before the target
+//! token paying the most attention to it, if the attention was returned.
+//!
+//! ```
+//! use ctranslate2::mask::Masker;
+//!
+//! let masker = Masker::default();
+//! let (masked, spans) = masker.mask("Hello <b>{name}</b>, see https://example.com.");
+//! assert_eq!(masked, "Hello ｟0｠｟1｠｟2｠, see ｟3｠.");
+//!
+//! let (text, report) = masker.restore("Hallo ｟0｠｟1｠｟2｠, siehe ｟3｠.", &spans);
+//! assert_eq!(text, "Hallo <b>{name}</b>, siehe https://example.com.");
+//! assert!(report.is_ok());
+//! ```
+
+use regex::Regex;
+
+const DEFAULT_OPEN: &str = "｟";
+const DEFAULT_CLOSE: &str = "｠";
+
+const URL_PATTERN: &str = r#"\b(?:(?:https?|ftp)://|www\.)[^\s<>"]*[^\s<>".,;:!?)\]}']"#;
+const MARKUP_PATTERN: &str =
+    r"<!--.*?-->|</?[A-Za-z][\w:.-]*(?:\s[^<>]*)?/?>|&(?:[A-Za-z]+|#[0-9]+|#x[0-9A-Fa-f]+);";
+const PLACEHOLDER_PATTERN: &str = r"\{\{[^{}]*\}\}|\$?\{[\w.:-]*\}|%(?:[0-9]+\$)?[-+#0]*[0-9]*(?:\.[0-9]+)?(?:hh|h|ll|l|L|z|j|t)?[diouxXeEfFgGaAcsp@]";
+
+/// A kind of span masked by a [`Masker`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MaskKind {
+    /// URLs such as `https://example.com/a?b=c` and `www.example.com`.
+    Url,
+    /// XML and HTML tags, comments and entities such as `<b>`, `</b>`, `<br/>` and `&amp;`.
+    Markup,
+    /// Placeholders such as `{name}`, `{0}`, `{{var}}`, `${var}`, `%s` and `%1$d`.
+    Placeholder,
+}
+
+impl MaskKind {
+    fn pattern(&self) -> &'static str {
+        match self {
+            MaskKind::Url => URL_PATTERN,
+            MaskKind::Markup => MARKUP_PATTERN,
+            MaskKind::Placeholder => PLACEHOLDER_PATTERN,
+        }
+    }
+}
+
+/// What happened to the sentinels of a translation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaskReport {
+    /// The spans whose sentinel is missing from the translation.
+    pub missing: Vec<usize>,
+    /// The spans whose sentinel appears more than once. Only the first one is restored.
+    pub duplicated: Vec<usize>,
+    /// The spans whose sentinel was missing and was inserted again using the attention.
+    pub reinserted: Vec<usize>,
+}
+
+impl MaskReport {
+    /// Returns whether every span was restored exactly once.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.duplicated.is_empty()
+    }
+}
+
+/// Replaces spans of a source with sentinels and restores them in the translation.
+#[derive(Clone, Debug)]
+pub struct Masker {
+    patterns: Vec<String>,
+    regex: Option<Regex>,
+    open: String,
+    close: String,
+    sentinel: Regex,
+    reinsertion: bool,
+}
+
+impl Default for Masker {
+    /// Masks URLs, markup and placeholders.
+    fn default() -> Self {
+        Self::new(&[MaskKind::Url, MaskKind::Markup, MaskKind::Placeholder])
+    }
+}
+
+impl Masker {
+    /// Creates a masker for the given kinds of spans. When spans overlap, the kind given first
+    /// wins.
+    pub fn new(kinds: &[MaskKind]) -> Self {
+        let mut res = Self {
+            patterns: Vec::new(),
+            regex: None,
+            open: String::new(),
+            close: String::new(),
+            sentinel: sentinel_regex(DEFAULT_OPEN, DEFAULT_CLOSE),
+            reinsertion: false,
+        };
+        for kind in kinds {
+            res.push_pattern(kind.pattern().to_owned())
+                .unwrap_or_else(|_| unreachable!("built-in patterns are valid"));
+        }
+        res.open = DEFAULT_OPEN.to_owned();
+        res.close = DEFAULT_CLOSE.to_owned();
+        res
+    }
+
+    /// Also masks the matches of a regular expression.
+    pub fn with_pattern(&mut self, pattern: &str) -> Result<&mut Self, regex::Error> {
+        self.push_pattern(pattern.to_owned())?;
+        Ok(self)
+    }
+
+    /// Sets the text around the number of a sentinel, `｟` and `｠` by default. They should be
+    /// copied unchanged by the model, and must not appear in the sources.
+    pub fn with_sentinels(&mut self, open: &str, close: &str) -> &mut Self {
+        self.open = open.to_owned();
+        self.close = close.to_owned();
+        self.sentinel = sentinel_regex(open, close);
+        self
+    }
+
+    /// Sets whether missing sentinels are inserted again using the attention. The attention
+    /// must be requested with `return_attention`.
+    pub fn with_reinsertion(&mut self, reinsertion: bool) -> &mut Self {
+        self.reinsertion = reinsertion;
+        self
+    }
+
+    /// Returns whether missing sentinels are inserted again using the attention.
+    pub fn reinsertion(&self) -> bool {
+        self.reinsertion
+    }
+
+    fn push_pattern(&mut self, pattern: String) -> Result<(), regex::Error> {
+        self.patterns.push(pattern);
+        let alternation = self
+            .patterns
+            .iter()
+            .map(|p| format!("(?:{p})"))
+            .collect::<Vec<_>>()
+            .join("|");
+        match Regex::new(&alternation) {
+            Ok(regex) => {
+                self.regex = Some(regex);
+                Ok(())
+            }
+            Err(err) => {
+                self.patterns.pop();
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns the sentinel of the span with the given index.
+    pub fn sentinel(&self, idx: usize) -> String {
+        format!("{}{idx}{}", self.open, self.close)
+    }
+
+    /// Returns the span index and byte range of every sentinel in a text.
+    pub fn find_sentinels(&self, text: &str) -> Vec<(usize, (usize, usize))> {
+        self.sentinel
+            .captures_iter(text)
+            .filter_map(|c| {
+                let m = c.get(0)?;
+                Some((c[1].parse().ok()?, (m.start(), m.end())))
+            })
+            .collect()
+    }
+
+    /// Replaces the spans to mask with sentinels. Returns the masked text and the spans.
+    pub fn mask(&self, text: &str) -> (String, Vec<String>) {
+        let Some(regex) = &self.regex else {
+            return (text.to_owned(), Vec::new());
+        };
+        let mut spans = Vec::new();
+        let masked = regex.replace_all(text, |c: &regex::Captures| {
+            spans.push(c[0].to_owned());
+            self.sentinel(spans.len() - 1)
+        });
+        (masked.into_owned(), spans)
+    }
+
+    /// Replaces the sentinels of a translation with the spans they stand for.
+    pub fn restore(&self, text: &str, spans: &[String]) -> (String, MaskReport) {
+        let mut report = MaskReport::default();
+        let mut seen = vec![0; spans.len()];
+        let mut res = String::with_capacity(text.len());
+        let mut last = 0;
+        for (idx, (start, end)) in self.find_sentinels(text) {
+            let Some(span) = spans.get(idx) else {
+                continue;
+            };
+            res.push_str(&text[last..start]);
+            seen[idx] += 1;
+            if seen[idx] == 1 {
+                res.push_str(span);
+            }
+            last = end;
+        }
+        res.push_str(&text[last..]);
+
+        for (idx, &count) in seen.iter().enumerate() {
+            match count {
+                0 => report.missing.push(idx),
+                1 => {}
+                _ => report.duplicated.push(idx),
+            }
+        }
+        (res, report)
+    }
+}
+
+/// Returns the pattern of the sentinels, tolerating spaces added around the number by the
+/// tokenizer.
+fn sentinel_regex(open: &str, close: &str) -> Regex {
+    let pattern = format!(
+        r"{}\s*([0-9]+)\s*{}",
+        regex::escape(open.trim()),
+        regex::escape(close.trim())
+    );
+    // The escaped delimiters around a number always form a valid pattern.
+    Regex::new(&pattern).unwrap_or_else(|err| unreachable!("{err}"))
+}
+
+#[test]
+fn mask_spans() {
+    let masker = Masker::default();
+    let (masked, spans) =
+        masker.mask("<a href=\"www.x.org\">50% off</a> for %1$s &amp; {{user}} at 5%.");
+    assert_eq!(masked, "｟0｠50% off｟1｠ for ｟2｠ ｟3｠ ｟4｠ at 5%.");
+    assert_eq!(spans[0], "<a href=\"www.x.org\">");
+
+    let (text, report) = masker.restore("｟ 0 ｠-｟1｠ ｟2｠ ｟2｠ ｟9｠", &spans);
+    assert_eq!(text, "<a href=\"www.x.org\">-</a> %1$s  ｟9｠");
+    assert_eq!(report.missing, [3, 4]);
+    assert_eq!(report.duplicated, [2]);
+    assert!(!report.is_ok());
+
+    let mut masker = Masker::new(&[]);
+    masker
+        .with_pattern(r"\bACME\b")
+        .unwrap()
+        .with_sentinels("<x", ">");
+    assert_eq!(masker.mask("ACME <b>").0, "<x0> <b>");
+    assert!(masker.with_pattern("(").is_err());
+}
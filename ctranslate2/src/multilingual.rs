@@ -0,0 +1,177 @@
+//! Language codes of many-to-many models.
+//!
+//! Multilingual models select the languages with special tokens: the source language token is
+//! added to the encoder input, and the target language token is forced as the first target
+//! token. The token format and position depend on the model family:
+//!
+//! | Family               | Code       | Token      | Source                    |
+//! |----------------------|------------|------------|---------------------------|
+//! | [`ModelFamily::Nllb`]    | `eng_Latn` | `eng_Latn` | `eng_Latn ▁Hello </s>`    |
+//! | [`ModelFamily::M2m100`]  | `en`       | `__en__`   | `__en__ ▁Hello </s>`      |
+//! | [`ModelFamily::Mbart50`] | `en_XX`    | `en_XX`    | `en_XX ▁Hello </s>`       |
+//! | [`ModelFamily::Mbart`]   | `en_XX`    | `en_XX`    | `▁Hello </s> en_XX`       |
+//!
+//! [`Languages`] combines a family with the model vocabulary, so that unknown language codes
+//! are rejected before translating. It is used by
+//! [`Translator2::translate_batch_to`](crate::Translator2::translate_batch_to).
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+
+use crate::tokenizer::LanguageTagPosition;
+
+const SHARED_VOCABULARY: &str = "shared_vocabulary";
+const SOURCE_VOCABULARY: &str = "source_vocabulary";
+const TARGET_VOCABULARY: &str = "target_vocabulary";
+
+/// A family of multilingual models sharing the same language token convention.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ModelFamily {
+    /// NLLB-200, with FLORES-200 codes such as `eng_Latn`.
+    Nllb,
+    /// M2M100, with ISO 639-1 codes such as `en`, written `__en__`.
+    M2m100,
+    /// mBART-50, with codes such as `en_XX` placed before the source.
+    Mbart50,
+    /// mBART (cc25), with codes such as `en_XX` placed after the source.
+    Mbart,
+}
+
+impl ModelFamily {
+    /// Returns the token of the given language code.
+    pub fn language_token(&self, code: &str) -> String {
+        match self {
+            ModelFamily::M2m100 => format!("__{code}__"),
+            ModelFamily::Nllb | ModelFamily::Mbart50 | ModelFamily::Mbart => code.to_owned(),
+        }
+    }
+
+    /// Returns where the source language token is placed.
+    pub fn source_position(&self) -> LanguageTagPosition {
+        match self {
+            ModelFamily::Mbart => LanguageTagPosition::Suffix,
+            _ => LanguageTagPosition::Prefix,
+        }
+    }
+
+    /// Inserts the source language token into a tokenized source sentence.
+    pub fn insert_source_token(&self, mut tokens: Vec<String>, token: &str) -> Vec<String> {
+        match self.source_position() {
+            LanguageTagPosition::Prefix => tokens.insert(0, token.to_owned()),
+            LanguageTagPosition::Suffix => tokens.push(token.to_owned()),
+        }
+        tokens
+    }
+}
+
+/// A model family together with the vocabularies used to validate language codes.
+#[derive(Clone, Debug)]
+pub struct Languages {
+    family: ModelFamily,
+    source_vocabulary: Option<HashSet<String>>,
+    target_vocabulary: Option<HashSet<String>>,
+}
+
+impl Languages {
+    /// Creates a configuration without vocabulary; every language code is accepted.
+    pub fn new(family: ModelFamily) -> Self {
+        Self {
+            family,
+            source_vocabulary: None,
+            target_vocabulary: None,
+        }
+    }
+
+    /// Creates a configuration validating language codes against the vocabularies of the
+    /// CTranslate2 model in the given directory: `shared_vocabulary`, or `source_vocabulary`
+    /// and `target_vocabulary`, in `.json` or `.txt` format.
+    pub fn from_model_dir<P: AsRef<Path>>(family: ModelFamily, model_dir: P) -> Result<Self> {
+        let dir = model_dir.as_ref();
+        let (source, target) = match read_vocabulary(dir, SHARED_VOCABULARY)? {
+            Some(shared) => (shared.clone(), shared),
+            None => (
+                read_vocabulary(dir, SOURCE_VOCABULARY)?
+                    .ok_or_else(|| anyhow!("no vocabulary found in {}", dir.display()))?,
+                read_vocabulary(dir, TARGET_VOCABULARY)?
+                    .ok_or_else(|| anyhow!("no target vocabulary found in {}", dir.display()))?,
+            ),
+        };
+        Ok(Self {
+            family,
+            source_vocabulary: Some(source),
+            target_vocabulary: Some(target),
+        })
+    }
+
+    /// Returns the model family.
+    pub fn family(&self) -> ModelFamily {
+        self.family
+    }
+
+    /// Returns the source token of the given language code, checking it is in the vocabulary.
+    pub fn source_token(&self, code: &str) -> Result<String> {
+        Self::validated(
+            self.family.language_token(code),
+            &self.source_vocabulary,
+            "source",
+        )
+    }
+
+    /// Returns the target token of the given language code, checking it is in the vocabulary.
+    pub fn target_token(&self, code: &str) -> Result<String> {
+        Self::validated(
+            self.family.language_token(code),
+            &self.target_vocabulary,
+            "target",
+        )
+    }
+
+    fn validated(token: String, vocab: &Option<HashSet<String>>, side: &str) -> Result<String> {
+        match vocab {
+            Some(vocab) if !vocab.contains(&token) => Err(anyhow!(
+                "unsupported {side} language: {token} is not in the model vocabulary"
+            )),
+            _ => Ok(token),
+        }
+    }
+}
+
+/// Reads `<name>.json` or `<name>.txt`, returning `None` if neither exists.
+fn read_vocabulary(dir: &Path, name: &str) -> Result<Option<HashSet<String>>> {
+    let json = dir.join(format!("{name}.json"));
+    if json.is_file() {
+        let content = fs::read_to_string(&json)
+            .map_err(|err| anyhow!("failed to read {}: {err}", json.display()))?;
+        let tokens: Vec<String> = serde_json::from_str(&content)
+            .map_err(|err| anyhow!("failed to parse {}: {err}", json.display()))?;
+        return Ok(Some(tokens.into_iter().collect()));
+    }
+
+    let txt = dir.join(format!("{name}.txt"));
+    if txt.is_file() {
+        let content = fs::read_to_string(&txt)
+            .map_err(|err| anyhow!("failed to read {}: {err}", txt.display()))?;
+        return Ok(Some(content.lines().map(str::to_owned).collect()));
+    }
+    Ok(None)
+}
+
+#[test]
+fn language_tokens() {
+    let tokens = vec!["▁Hi".to_owned(), "</s>".to_owned()];
+    assert_eq!(
+        ModelFamily::Mbart.insert_source_token(tokens, "en_XX"),
+        ["▁Hi", "</s>", "en_XX"]
+    );
+
+    let languages = Languages {
+        family: ModelFamily::Nllb,
+        source_vocabulary: Some(HashSet::from(["eng_Latn".to_owned()])),
+        target_vocabulary: Some(HashSet::from(["deu_Latn".to_owned()])),
+    };
+    assert!(languages.source_token("eng_Latn").is_ok());
+    assert!(languages.target_token("eng_Latn").is_err());
+}
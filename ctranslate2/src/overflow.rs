@@ -0,0 +1,144 @@
+//! Sources longer than `max_input_length`.
+//!
+//! CTranslate2 truncates the sources longer than
+//! [`TranslationOptions::max_input_length`](crate::TranslationOptions::max_input_length) tokens,
+//! so the end of them is missing from the translation. [`OverflowPolicy`] selects what
+//! [`Translator2`](crate::Translator2) does with such sources instead:
+//!
+//! - [`OverflowPolicy::Truncate`] passes them to CTranslate2 unchanged.
+//! - [`OverflowPolicy::Error`] fails the batch.
+//! - [`OverflowPolicy::Split`] splits them into chunks that fit, translates the chunks and
+//!   joins their translations with the whitespace found between the chunks. A source is split
+//!   at the sentence boundary closest to its middle, or else at a clause boundary, a space or a
+//!   character boundary, until every chunk fits.
+
+use anyhow::Result;
+
+use crate::segment::Segmenter;
+
+const CLAUSE_PUNCTUATION: &[char] = &[',', ';', ':'];
+const CJK_CLAUSE_PUNCTUATION: &[char] = &['，', '；', '：', '、'];
+
+/// What [`Translator2`](crate::Translator2) does with sources longer than `max_input_length`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Lets CTranslate2 truncate them.
+    #[default]
+    Truncate,
+    /// Returns an error.
+    Error,
+    /// Splits them into chunks translated separately.
+    Split,
+}
+
+/// Splits a text into the byte ranges of chunks for which `fits` returns true. The whitespace
+/// around the cuts is left out of the chunks.
+pub(crate) fn split<F>(text: &str, fits: &mut F) -> Result<Vec<(usize, usize)>>
+where
+    F: FnMut(&str) -> Result<bool>,
+{
+    let start = text.len() - text.trim_start().len();
+    let mut res = Vec::new();
+    split_range(
+        text,
+        start,
+        text.trim_end().len().max(start),
+        fits,
+        &mut res,
+    )?;
+    Ok(res)
+}
+
+fn split_range<F>(
+    text: &str,
+    start: usize,
+    end: usize,
+    fits: &mut F,
+    res: &mut Vec<(usize, usize)>,
+) -> Result<()>
+where
+    F: FnMut(&str) -> Result<bool>,
+{
+    let piece = &text[start..end];
+    if fits(piece)? {
+        res.push((start, end));
+        return Ok(());
+    }
+    let cuts = [cut_point(piece), middle(piece)];
+    let Some((left, right)) = cuts.into_iter().flatten().find_map(|cut| {
+        let left = start + piece[..cut].trim_end().len();
+        let right = end - piece[cut..].trim_start().len();
+        (start < left && right < end).then_some((left, right))
+    }) else {
+        // A single character: CTranslate2 truncates it if it is still too long.
+        res.push((start, end));
+        return Ok(());
+    };
+    split_range(text, start, left, fits, res)?;
+    split_range(text, right, end, fits, res)
+}
+
+/// Returns the preferred position to split a text at.
+fn cut_point(text: &str) -> Option<usize> {
+    let closest = |candidates: &mut dyn Iterator<Item = usize>| {
+        candidates
+            .filter(|&p| 0 < p && p < text.len())
+            .min_by_key(|&p| p.abs_diff(text.len() / 2))
+    };
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let after = |idx: usize| chars.get(idx + 1).map_or(text.len(), |&(p, _)| p);
+
+    let sentences = Segmenter::new("").split_with_offsets(text);
+    closest(&mut sentences.into_iter().map(|(_, end)| end))
+        .or_else(|| {
+            closest(&mut (0..chars.len()).filter_map(|idx| {
+                let c = chars[idx].1;
+                let next_space = chars.get(idx + 1).is_some_and(|(_, n)| n.is_whitespace());
+                (CJK_CLAUSE_PUNCTUATION.contains(&c)
+                    || (CLAUSE_PUNCTUATION.contains(&c) && next_space))
+                    .then(|| after(idx))
+            }))
+        })
+        .or_else(|| {
+            closest(
+                &mut chars
+                    .iter()
+                    .filter(|(_, c)| c.is_whitespace())
+                    .map(|&(p, _)| p),
+            )
+        })
+}
+
+/// Returns the character boundary closest to the middle of a text.
+fn middle(text: &str) -> Option<usize> {
+    (text.len() / 2..text.len())
+        .find(|&p| text.is_char_boundary(p))
+        .filter(|&p| p > 0)
+}
+
+#[test]
+fn split_long_text() {
+    let mut fits = |s: &str| Ok(s.split_whitespace().count() <= 4);
+    let text = " One two three. Four five, six seven eight nine ten eleven.  ";
+    let chunks = split(text, &mut fits)
+        .unwrap()
+        .into_iter()
+        .map(|(start, end)| &text[start..end])
+        .collect::<Vec<_>>();
+    assert_eq!(
+        chunks,
+        [
+            "One two three.",
+            "Four five,",
+            "six seven eight",
+            "nine ten eleven."
+        ]
+    );
+
+    let mut fits = |s: &str| Ok(s.chars().count() <= 2);
+    let text = "一二三四五";
+    let chunks = split(text, &mut fits).unwrap();
+    assert!(chunks.iter().all(|&(s, e)| text[s..e].chars().count() <= 2));
+    assert_eq!(chunks.first().map(|c| c.0), Some(0));
+    assert_eq!(chunks.last().map(|c| c.1), Some(text.len()));
+}
@@ -0,0 +1,310 @@
+//! Text processing around translation.
+//!
+//! A [`Processor`] rewrites the sources before they are tokenized and the translations after
+//! they are decoded. Processors are chained in a [`Pipeline`]: sources go through the
+//! processors in order and translations in reverse order, so the first processor sees the
+//! raw input and the final output. Each processor can record data about a source in its
+//! [`Metadata`], e.g. the whitespace it trimmed, and use it when post-processing the
+//! translation of that source.
+//!
+//! | Processor              | Source                                    | Translation                           |
+//! |------------------------|-------------------------------------------|---------------------------------------|
+//! | [`Nfkc`]               | Unicode NFKC normalization                |                                       |
+//! | [`Whitespace`]         | Collapses and trims whitespace            | Restores the trimmed whitespace       |
+//! | [`ControlChars`]       | Removes control and zero-width characters |                                       |
+//! | [`Quotes`]             | Replaces typographic quotes with ASCII    |                                       |
+//! | [`PunctuationSpacing`] |                                           | Removes spaces around punctuation     |
+//! | [`CjkSpacing`]         |                                           | Removes spaces between CJK characters |
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use ctranslate2::Translator2;
+//! use ctranslate2::processor::{ControlChars, Nfkc, PunctuationSpacing, Whitespace};
+//! use ctranslate2::tokenizer::auto;
+//!
+//! # fn main() -> Result<()> {
+//! let path = "/path/to/model";
+//! let mut t = Translator2::new(path, &Default::default(), auto::load(path)?)?;
+//! t.with_processor(Nfkc)
+//!     .with_processor(ControlChars)
+//!     .with_processor(Whitespace)
+//!     .with_processor(PunctuationSpacing);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use unicode_normalization::UnicodeNormalization;
+
+/// Data recorded about a source while pre-processing it, and read back while post-processing
+/// its translation. Processors should prefix their keys with their name.
+pub type Metadata = HashMap<String, String>;
+
+/// Rewrites sources before tokenization and translations after decoding.
+pub trait Processor: Send + Sync {
+    /// Rewrites a source before it is tokenized.
+    fn preprocess(&self, text: String, _metadata: &mut Metadata) -> Result<String> {
+        Ok(text)
+    }
+
+    /// Rewrites the translation of a source after it is decoded.
+    fn postprocess(&self, text: String, _metadata: &Metadata) -> Result<String> {
+        Ok(text)
+    }
+
+    /// Returns what identifies the processor in the keys of the [`cache`](crate::cache), its
+    /// type name by default. Processors whose output depends on their settings should include
+    /// the settings.
+    fn id(&self) -> String {
+        std::any::type_name::<Self>().to_owned()
+    }
+}
+
+/// A chain of processors.
+#[derive(Default)]
+pub struct Pipeline {
+    processors: Vec<Box<dyn Processor>>,
+}
+
+impl Pipeline {
+    /// Creates an empty pipeline.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a processor to the chain.
+    pub fn push<P: Processor + 'static>(&mut self, processor: P) -> &mut Self {
+        self.processors.push(Box::new(processor));
+        self
+    }
+
+    /// Returns the number of processors.
+    pub fn len(&self) -> usize {
+        self.processors.len()
+    }
+
+    /// Returns whether the pipeline has no processor.
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+}
+
+impl Processor for Pipeline {
+    fn preprocess(&self, text: String, metadata: &mut Metadata) -> Result<String> {
+        self.processors
+            .iter()
+            .try_fold(text, |text, p| p.preprocess(text, metadata))
+    }
+
+    fn postprocess(&self, text: String, metadata: &Metadata) -> Result<String> {
+        self.processors
+            .iter()
+            .rev()
+            .try_fold(text, |text, p| p.postprocess(text, metadata))
+    }
+
+    fn id(&self) -> String {
+        let ids = self.processors.iter().map(|p| p.id()).collect::<Vec<_>>();
+        format!("[{}]", ids.join(","))
+    }
+}
+
+/// Applies Unicode NFKC normalization to the sources, e.g. `ﬁ` becomes `fi` and full-width
+/// letters become ASCII.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Nfkc;
+
+impl Processor for Nfkc {
+    fn preprocess(&self, text: String, _metadata: &mut Metadata) -> Result<String> {
+        Ok(text.nfkc().collect())
+    }
+}
+
+/// Collapses runs of whitespace in the sources into single spaces and trims them. The
+/// leading and trailing whitespace is added back to the translations.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Whitespace;
+
+impl Whitespace {
+    const LEADING: &str = "whitespace.leading";
+    const TRAILING: &str = "whitespace.trailing";
+}
+
+impl Processor for Whitespace {
+    fn preprocess(&self, text: String, metadata: &mut Metadata) -> Result<String> {
+        let trimmed = text.trim_start();
+        let leading = &text[..text.len() - trimmed.len()];
+        let trailing = &trimmed[trimmed.trim_end().len()..];
+        metadata.insert(Self::LEADING.to_owned(), leading.to_owned());
+        metadata.insert(Self::TRAILING.to_owned(), trailing.to_owned());
+        Ok(text.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+
+    fn postprocess(&self, text: String, metadata: &Metadata) -> Result<String> {
+        let get = |key| metadata.get(key).map(String::as_str).unwrap_or_default();
+        Ok(format!(
+            "{}{}{}",
+            get(Self::LEADING),
+            text.trim(),
+            get(Self::TRAILING)
+        ))
+    }
+}
+
+/// Removes control characters other than whitespace, and zero-width characters, from the
+/// sources.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ControlChars;
+
+impl Processor for ControlChars {
+    fn preprocess(&self, text: String, _metadata: &mut Metadata) -> Result<String> {
+        Ok(text
+            .chars()
+            .filter(|&c| {
+                (!c.is_control() || c.is_whitespace())
+                    && !matches!(
+                        c,
+                        '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}'
+                    )
+            })
+            .collect())
+    }
+}
+
+/// Replaces typographic quotes and primes in the sources with ASCII quotes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Quotes;
+
+impl Processor for Quotes {
+    fn preprocess(&self, text: String, _metadata: &mut Metadata) -> Result<String> {
+        Ok(text
+            .chars()
+            .map(|c| match c {
+                '‘' | '’' | '‚' | '‛' | '′' => '\'',
+                '“' | '”' | '„' | '‟' | '″' => '"',
+                c => c,
+            })
+            .collect())
+    }
+}
+
+/// Removes the spaces detokenizers leave before closing punctuation and after opening
+/// brackets in the translations, e.g. `Hello , world ( again )` becomes
+/// `Hello, world (again)`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PunctuationSpacing;
+
+impl PunctuationSpacing {
+    fn is_closing(c: char) -> bool {
+        matches!(c, ',' | '.' | '!' | '?' | ';' | ':' | ')' | ']' | '}' | '%')
+    }
+
+    fn is_opening(c: char) -> bool {
+        matches!(c, '(' | '[' | '{')
+    }
+}
+
+impl Processor for PunctuationSpacing {
+    fn postprocess(&self, text: String, _metadata: &Metadata) -> Result<String> {
+        let mut res = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == ' ' {
+                let next = chars.peek().copied();
+                let prev = res.chars().next_back();
+                if next.is_some_and(Self::is_closing) || prev.is_some_and(Self::is_opening) {
+                    continue;
+                }
+            }
+            res.push(c);
+        }
+        Ok(res)
+    }
+}
+
+/// Removes the spaces between CJK characters in the translations, which word-based
+/// detokenizers insert, e.g. `你好 ， 世界` becomes `你好，世界`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CjkSpacing;
+
+impl CjkSpacing {
+    pub(crate) fn is_cjk(c: char) -> bool {
+        matches!(c,
+            '\u{2E80}'..='\u{2FDF}'   // CJK radicals
+            | '\u{3000}'..='\u{30FF}' // CJK punctuation, hiragana and katakana
+            | '\u{3100}'..='\u{31FF}' // bopomofo and katakana extensions
+            | '\u{3400}'..='\u{4DBF}' // CJK extension A
+            | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+            | '\u{AC00}'..='\u{D7AF}' // hangul syllables
+            | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+            | '\u{FF00}'..='\u{FFEF}' // half-width and full-width forms
+            | '\u{20000}'..='\u{2FA1F}'
+        )
+    }
+
+    /// Hangul separates words with spaces, so spaces next to it are kept.
+    pub(crate) fn is_hangul(c: char) -> bool {
+        matches!(c, '\u{AC00}'..='\u{D7AF}')
+    }
+}
+
+impl Processor for CjkSpacing {
+    fn postprocess(&self, text: String, _metadata: &Metadata) -> Result<String> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut res = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c.is_whitespace() && i > 0 {
+                let end = (i..chars.len())
+                    .find(|&j| !chars[j].is_whitespace())
+                    .unwrap_or(chars.len());
+                let (prev, next) = (chars[i - 1], chars.get(end).copied());
+                if let Some(next) = next
+                    && Self::is_cjk(prev)
+                    && Self::is_cjk(next)
+                    && !(Self::is_hangul(prev) && Self::is_hangul(next))
+                {
+                    i = end;
+                    continue;
+                }
+            }
+            res.push(c);
+            i += 1;
+        }
+        Ok(res)
+    }
+}
+
+#[test]
+fn process_text() {
+    let mut pipeline = Pipeline::new();
+    pipeline
+        .push(Nfkc)
+        .push(ControlChars)
+        .push(Quotes)
+        .push(Whitespace)
+        .push(PunctuationSpacing)
+        .push(CjkSpacing);
+
+    let mut metadata = Metadata::new();
+    let source = pipeline
+        .preprocess(
+            " \u{FEFF}“ﬁne”\u{7}  day\u{200B}\n".to_owned(),
+            &mut metadata,
+        )
+        .unwrap();
+    assert_eq!(source, "\"fine\" day");
+
+    let target = pipeline
+        .postprocess("Schöner Tag ( heute ) !".to_owned(), &metadata)
+        .unwrap();
+    assert_eq!(target, " Schöner Tag (heute)!\n");
+
+    let target = pipeline
+        .postprocess("你好 ， 世界 。 안녕 하세요".to_owned(), &Metadata::new())
+        .unwrap();
+    assert_eq!(target, "你好，世界。안녕 하세요");
+}
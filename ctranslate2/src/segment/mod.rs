@@ -0,0 +1,335 @@
+//! Sentence segmentation.
+//!
+//! Translation models are trained on single sentences, so paragraphs should be split before
+//! translating them. A [`Segmenter`] returns the byte spans of the sentences of a text:
+//!
+//! - [`Segmenter::new`] uses rules similar to the Moses `split-sentences.perl`: a sentence
+//!   ends with `.`, `!`, `?` or `…`, optionally followed by closing quotes and brackets, when
+//!   the next word does not start with a lowercase letter. A period after an abbreviation of
+//!   the language, e.g. `Dr.` in English, does not end a sentence. The abbreviations of
+//!   English, German and French are built in; more can be added with
+//!   [`Segmenter::with_abbreviations`] or [`Segmenter::with_abbreviation_file`].
+//! - CJK sentences end with `。`, `！` or `？`, with or without a following space.
+//! - [`Segmenter::from_srx`] uses the language rules of an
+//!   [SRX 2.0](https://www.unicode.org/uli/pas/srx/) file instead, provided their regular
+//!   expressions do without look-around.
+//!
+//! Blank lines always separate sentences, so paragraphs are never merged.
+//! [`Translator2::translate_document`](crate::Translator2::translate_document) uses a segmenter
+//! to translate whole documents.
+//!
+//! ```
+//! use ctranslate2::segment::Segmenter;
+//!
+//! let s = Segmenter::new("en");
+//! assert_eq!(
+//!     s.split("Dr. Smith arrived. He said \"Hi!\" and left.\n\nThe end."),
+//!     ["Dr. Smith arrived.", "He said \"Hi!\" and left.", "The end."]
+//! );
+//! ```
+
+mod srx;
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::tokenizer::moses::{self, Prefix};
+
+const TERMINATORS: &[char] = &['.', '!', '?', '…'];
+const CJK_TERMINATORS: &[char] = &['。', '！', '？'];
+const CLOSERS: &[char] = &[
+    '"', '\'', '”', '’', '»', '›', ')', ']', '}', '」', '』', '）', '》', '〉', '】',
+];
+const OPENERS: &[char] = &['"', '\'', '“', '‘', '«', '‹', '(', '[', '{', '¿', '¡'];
+
+/// An error loading segmentation rules.
+#[derive(Debug)]
+pub enum SegmentError {
+    /// The rule file does not exist.
+    FileNotFound(PathBuf),
+    /// The rule file exists but could not be loaded.
+    Parse { path: PathBuf, message: String },
+    /// The SRX file has no rules for the language.
+    UnsupportedLanguage { path: PathBuf, lang: String },
+}
+
+impl SegmentError {
+    fn check_file(path: &Path) -> Result<(), SegmentError> {
+        if path.is_file() {
+            Ok(())
+        } else {
+            Err(SegmentError::FileNotFound(path.to_path_buf()))
+        }
+    }
+
+    fn parse<E: fmt::Display>(path: &Path, err: E) -> SegmentError {
+        SegmentError::Parse {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SegmentError::FileNotFound(path) => {
+                write!(f, "Segmentation rule file not found: {}", path.display())
+            }
+            SegmentError::Parse { path, message } => {
+                write!(
+                    f,
+                    "Failed to load segmentation rules {}: {}",
+                    path.display(),
+                    message
+                )
+            }
+            SegmentError::UnsupportedLanguage { path, lang } => {
+                write!(
+                    f,
+                    "No segmentation rules for {} in {}",
+                    lang,
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SegmentError {}
+
+#[derive(Clone, Debug)]
+enum Rules {
+    Builtin(HashMap<String, Prefix>),
+    Srx(Vec<srx::Rule>),
+}
+
+/// Splits texts into sentences.
+#[derive(Clone, Debug)]
+pub struct Segmenter {
+    lang: String,
+    rules: Rules,
+}
+
+impl Segmenter {
+    /// Creates a segmenter with the built-in rules and abbreviations of the given language
+    /// code, e.g. `en`.
+    pub fn new(lang: &str) -> Self {
+        let mut prefixes = HashMap::new();
+        moses::parse_prefixes(moses::builtin_prefixes(lang), &mut prefixes);
+        Self {
+            lang: lang.to_owned(),
+            rules: Rules::Builtin(prefixes),
+        }
+    }
+
+    /// Creates a segmenter with the rules an SRX file defines for the given language code.
+    ///
+    /// Only the language rules, the language maps and the `cascade` setting are read. The rules
+    /// are regular expressions of the [`regex`](https://docs.rs/regex/) crate, which does not
+    /// support look-around; files using it are rejected. A `beforebreak` pattern matches at
+    /// most the 256 bytes before a break.
+    pub fn from_srx<P: AsRef<Path>>(path: P, lang: &str) -> Result<Self, SegmentError> {
+        let path = path.as_ref();
+        SegmentError::check_file(path)?;
+        let content = fs::read_to_string(path).map_err(|err| SegmentError::parse(path, err))?;
+        let rules = srx::parse(&content, lang).map_err(|err| SegmentError::parse(path, err))?;
+        if rules.is_empty() {
+            return Err(SegmentError::UnsupportedLanguage {
+                path: path.to_path_buf(),
+                lang: lang.to_owned(),
+            });
+        }
+        Ok(Self {
+            lang: lang.to_owned(),
+            rules: Rules::Srx(rules),
+        })
+    }
+
+    /// Adds abbreviations, written without their period, which do not end a sentence. Has no
+    /// effect on SRX rules.
+    pub fn with_abbreviations<I, S>(&mut self, abbreviations: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        if let Rules::Builtin(prefixes) = &mut self.rules {
+            prefixes.extend(
+                abbreviations
+                    .into_iter()
+                    .map(|a| (a.into(), Prefix::Always)),
+            );
+        }
+        self
+    }
+
+    /// Adds the abbreviations of a Moses `nonbreaking_prefix.<lang>` file. Has no effect on
+    /// SRX rules.
+    pub fn with_abbreviation_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<&mut Self, SegmentError> {
+        let path = path.as_ref();
+        SegmentError::check_file(path)?;
+        let content = fs::read_to_string(path).map_err(|err| SegmentError::parse(path, err))?;
+        if let Rules::Builtin(prefixes) = &mut self.rules {
+            moses::parse_prefixes(&content, prefixes);
+        }
+        Ok(self)
+    }
+
+    /// Returns the language code.
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    /// Splits a text into sentences, without their surrounding whitespace.
+    pub fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        self.split_with_offsets(text)
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .collect()
+    }
+
+    /// Returns the byte spans of the sentences of a text, without their surrounding
+    /// whitespace. The text between two spans is whitespace only.
+    pub fn split_with_offsets(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut breaks = paragraph_breaks(text);
+        match &self.rules {
+            Rules::Builtin(prefixes) => breaks.extend(builtin_breaks(prefixes, text)),
+            Rules::Srx(rules) => breaks.extend(srx::breaks(rules, text)),
+        }
+        breaks.insert(text.len());
+
+        let mut res = Vec::new();
+        let mut start = 0;
+        for end in breaks {
+            let piece = &text[start..end];
+            let trimmed = piece.trim_start();
+            let (s, e) = (
+                start + piece.len() - trimmed.len(),
+                start + piece.trim_end().len(),
+            );
+            if s < e {
+                res.push((s, e));
+            }
+            start = end;
+        }
+        res
+    }
+}
+
+/// Returns the start of the whitespace runs containing a blank line.
+fn paragraph_breaks(text: &str) -> BTreeSet<usize> {
+    let mut res = BTreeSet::new();
+    let mut run = None;
+    let mut newlines = 0;
+    for (idx, c) in text.char_indices() {
+        if c.is_whitespace() {
+            run.get_or_insert(idx);
+            newlines += usize::from(c == '\n');
+            continue;
+        }
+        if let Some(start) = run.take()
+            && newlines > 1
+        {
+            res.insert(start);
+        }
+        newlines = 0;
+    }
+    res
+}
+
+/// Returns the ends of the sentences found by the built-in rules.
+fn builtin_breaks(prefixes: &HashMap<String, Prefix>, text: &str) -> BTreeSet<usize> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let pos = |idx: usize| chars.get(idx).map_or(text.len(), |&(p, _)| p);
+    let skip = |mut idx: usize, set: &[char]| {
+        while idx < chars.len() && set.contains(&chars[idx].1) {
+            idx += 1;
+        }
+        idx
+    };
+
+    let mut res = BTreeSet::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i].1;
+        if CJK_TERMINATORS.contains(&c) {
+            let end = skip(skip(i, CJK_TERMINATORS), CLOSERS);
+            res.insert(pos(end));
+            i = end;
+            continue;
+        }
+        if !TERMINATORS.contains(&c) {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let run_end = skip(i, TERMINATORS);
+        let end = skip(run_end, CLOSERS);
+        i = end;
+        if end == chars.len() || !chars[end].1.is_whitespace() {
+            continue;
+        }
+        let next = (end..chars.len())
+            .find(|&idx| !chars[idx].1.is_whitespace())
+            .unwrap_or(chars.len());
+        let Some(&(_, first)) = chars.get(skip(next, OPENERS)) else {
+            continue;
+        };
+        if first.is_lowercase() {
+            continue;
+        }
+
+        // Only a single period may follow an abbreviation.
+        if c == '.' && run_end == start + 1 {
+            let word_start = chars[..start]
+                .iter()
+                .rposition(|(_, c)| c.is_whitespace())
+                .map_or(0, |idx| idx + 1);
+            let word = text[pos(word_start)..pos(start)].trim_start_matches(OPENERS);
+            let abbreviation = match prefixes.get(word) {
+                Some(Prefix::Always) => true,
+                Some(Prefix::NumericOnly) => first.is_numeric(),
+                None => word.contains('.') && word.contains(char::is_alphabetic),
+            };
+            if abbreviation {
+                continue;
+            }
+        }
+        res.insert(pos(end));
+    }
+    res
+}
+
+#[test]
+fn split_sentences() {
+    let s = Segmenter::new("en");
+    let text = "  Mr. Smith paid $3.50 (approx.) in the U.S. Really?! \"Yes.\" ok.\nNo. 5 wins.";
+    assert_eq!(
+        s.split(text),
+        [
+            "Mr. Smith paid $3.50 (approx.) in the U.S. Really?!",
+            "\"Yes.\" ok.",
+            "No. 5 wins.",
+        ]
+    );
+
+    let spans = s.split_with_offsets("One.  Two.\n\nthree");
+    assert_eq!(spans, [(0, 4), (6, 10), (12, 17)]);
+
+    let s = Segmenter::new("zh");
+    assert_eq!(
+        s.split("你好。我很好！「真的？」是的"),
+        ["你好。", "我很好！", "「真的？」", "是的"]
+    );
+
+    let mut s = Segmenter::new("xx");
+    assert_eq!(s.split("See approx. Ten."), ["See approx.", "Ten."]);
+    s.with_abbreviations(["approx"]);
+    assert_eq!(s.split("See approx. Ten."), ["See approx. Ten."]);
+}
@@ -0,0 +1,191 @@
+//! Segmentation rules of SRX 2.0 files.
+//!
+//! Only the parts of SRX used for sentence splitting are read: the `cascade` attribute of the
+//! header, the language rules and the language maps. The text before a break is matched
+//! within the last [`MAX_BEFORE_LEN`] bytes, so that splitting stays linear in the length of
+//! the text.
+
+use std::collections::{BTreeSet, HashMap};
+
+use regex::Regex;
+
+use crate::xml::{Event, Events, attr};
+
+/// The maximum length in bytes of the text matched by the `beforebreak` pattern of a rule.
+const MAX_BEFORE_LEN: usize = 256;
+
+/// A break or exception rule.
+#[derive(Clone, Debug)]
+pub(crate) struct Rule {
+    brk: bool,
+    /// The text before the break, anywhere in the text.
+    before: Regex,
+    /// The text before the break, ending at the break.
+    before_end: Regex,
+    /// The text after the break, starting at the break.
+    after_start: Regex,
+}
+
+impl Rule {
+    fn new(brk: bool, before: &str, after: &str) -> Result<Self, String> {
+        let compile = |pattern: String| {
+            Regex::new(&pattern).map_err(|err| format!("invalid rule {before:?} {after:?}: {err}"))
+        };
+        Ok(Self {
+            brk,
+            before: compile(format!("(?:{before})"))?,
+            before_end: compile(format!("(?:{before})\\z"))?,
+            after_start: compile(format!("\\A(?:{after})"))?,
+        })
+    }
+
+    /// Returns whether the rule matches at a position. The text before it is searched from
+    /// [`MAX_BEFORE_LEN`] bytes back only; assertions such as `\b` still see the whole text.
+    fn matches(&self, text: &str, pos: usize) -> bool {
+        let mut start = pos.saturating_sub(MAX_BEFORE_LEN);
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        self.before_end.is_match_at(&text[..pos], start) && self.after_start.is_match(&text[pos..])
+    }
+}
+
+/// Returns the break positions of a text. The first rule matching a position decides whether
+/// it is a break.
+pub(crate) fn breaks(rules: &[Rule], text: &str) -> BTreeSet<usize> {
+    let candidates = rules
+        .iter()
+        .filter(|r| r.brk)
+        .flat_map(|r| r.before.find_iter(text).map(|m| m.end()))
+        .filter(|&pos| 0 < pos && pos < text.len())
+        .collect::<BTreeSet<_>>();
+    candidates
+        .into_iter()
+        .filter(|&pos| {
+            rules
+                .iter()
+                .find(|r| r.matches(text, pos))
+                .is_some_and(|r| r.brk)
+        })
+        .collect()
+}
+
+/// Parses an SRX document and returns the rules of the given language, in the order they
+/// apply. Returns no rule if no language map matches.
+pub(crate) fn parse(content: &str, lang: &str) -> Result<Vec<Rule>, String> {
+    let mut cascade = false;
+    let mut language_rules = HashMap::<String, Vec<Rule>>::new();
+    let mut maps = Vec::new();
+
+    let mut current_language = None;
+    let mut current_rule = None;
+    let mut in_after = None;
+    let (mut before, mut after) = (String::new(), String::new());
+
+    for event in Events::new(content) {
+        match event? {
+            Event::Start(name, attrs) => match name {
+                "header" => cascade = attr(&attrs, "cascade") == Some("yes"),
+                "languagerule" => {
+                    current_language = Some(
+                        attr(&attrs, "languagerulename")
+                            .ok_or("languagerule without languagerulename")?
+                            .to_owned(),
+                    )
+                }
+                "rule" => {
+                    current_rule = Some(attr(&attrs, "break") != Some("no"));
+                    before.clear();
+                    after.clear();
+                }
+                "beforebreak" => in_after = Some(false),
+                "afterbreak" => in_after = Some(true),
+                "languagemap" => maps.push((
+                    attr(&attrs, "languagepattern")
+                        .ok_or("languagemap without languagepattern")?
+                        .to_owned(),
+                    attr(&attrs, "languagerulename")
+                        .ok_or("languagemap without languagerulename")?
+                        .to_owned(),
+                )),
+                _ => {}
+            },
+            Event::Text(t) => match in_after {
+                Some(false) => before.push_str(&t),
+                Some(true) => after.push_str(&t),
+                None => {}
+            },
+            Event::End(name) => match name {
+                "beforebreak" | "afterbreak" => in_after = None,
+                "rule" => {
+                    let (Some(brk), Some(language)) = (current_rule.take(), &current_language)
+                    else {
+                        continue;
+                    };
+                    language_rules
+                        .entry(language.clone())
+                        .or_default()
+                        .push(Rule::new(brk, &before, &after)?);
+                }
+                "languagerule" => current_language = None,
+                _ => {}
+            },
+        }
+    }
+
+    let mut res = Vec::new();
+    for (pattern, name) in maps {
+        let pattern = Regex::new(&format!("\\A(?:{pattern})\\z"))
+            .map_err(|err| format!("invalid language pattern {pattern:?}: {err}"))?;
+        if !pattern.is_match(lang) {
+            continue;
+        }
+        res.extend(language_rules.get(&name).into_iter().flatten().cloned());
+        if !cascade {
+            break;
+        }
+    }
+    Ok(res)
+}
+
+#[test]
+fn srx_rules() {
+    let srx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<srx xmlns="http://www.lisa.org/srx20" version="2.0">
+  <header segmentsubflows="yes" cascade="yes"/>
+  <body>
+    <languagerules>
+      <languagerule languagerulename="English">
+        <!-- Abbreviations -->
+        <rule break="no"><beforebreak>\bMr\.</beforebreak><afterbreak>\s</afterbreak></rule>
+      </languagerule>
+      <languagerule languagerulename="Default">
+        <rule break="yes"><beforebreak>[.?!]+</beforebreak><afterbreak>\s+\p{Lu}</afterbreak></rule>
+        <rule><beforebreak>&#x3002;</beforebreak><afterbreak></afterbreak></rule>
+      </languagerule>
+    </languagerules>
+    <maprules>
+      <languagemap languagepattern="en.*" languagerulename="English"/>
+      <languagemap languagepattern=".*" languagerulename="Default"/>
+    </maprules>
+  </body>
+</srx>"#;
+
+    let rules = parse(srx, "en").unwrap();
+    assert_eq!(rules.len(), 3);
+    let text = "Mr. Smith left. Bye!好。好";
+    assert_eq!(
+        breaks(&rules, text).into_iter().collect::<Vec<_>>(),
+        [
+            15,
+            text.find('好').unwrap() + '好'.len_utf8() + '。'.len_utf8()
+        ]
+    );
+
+    let rules = parse(srx, "de").unwrap();
+    assert_eq!(rules.len(), 2);
+    assert!(breaks(&rules, "Mr. Smith").contains(&3));
+    assert_eq!(breaks(&rules, &"Hi. Hé. ".repeat(10_000)).len(), 19_999);
+
+    assert!(parse(&srx.replace("[.?!]+", "(?<=a)"), "en").is_err());
+}
@@ -0,0 +1,233 @@
+//! Picks the tokenizer matching the files found in a model directory.
+//!
+//! [`load`] looks for the following files, in this order, and returns the first matching
+//! tokenizer:
+//!
+//! | Files                          | Tokenizer                                  | Feature                              |
+//! |--------------------------------|--------------------------------------------|--------------------------------------|
+//! | `tokenizer.json`               | `hf::Tokenizer`                            | `tokenizers`                         |
+//! | `source.spm` and `target.spm`  | SentencePiece, one model per side          | `rust_tokenizers` or `sentencepiece` |
+//! | `vocab.json` and `merges.txt`  | `bpe::from_file`                           | `tokenizers`                         |
+//! | `*.model`                      | SentencePiece, one model for both sides    | `rust_tokenizers` or `sentencepiece` |
+//!
+//! If the files are found but the required cargo feature is not enabled, the error names the
+//! feature to enable.
+//!
+//! SentencePiece tokenizers get the [`SpecialTokens`] read by [`SpecialTokens::from_model_dir`]
+//! from `config.json` and `tokenizer_config.json`, except the language tokens: pass the
+//! languages to [`Translator2::translate_batch_to`](crate::Translator2::translate_batch_to)
+//! instead. The other tokenizers add the special tokens configured in their own files.
+//! With `rust_tokenizers`, the unigram or BPE model type is read from the SentencePiece model;
+//! other model types are rejected.
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use ctranslate2::{Translator2, tokenizer::auto};
+//!
+//! # fn main() -> Result<()> {
+//! let path = "/path/to/model";
+//! let t = Translator2::new(path, &Default::default(), auto::load(path)?)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+
+use crate::Tokenizer;
+use crate::tokenizer::SpecialTokens;
+
+const TOKENIZER_FILE: &str = "tokenizer.json";
+const SOURCE_SPM_FILE: &str = "source.spm";
+const TARGET_SPM_FILE: &str = "target.spm";
+const VOCAB_FILE: &str = "vocab.json";
+const MERGES_FILE: &str = "merges.txt";
+const SPM_EXTENSION: &str = "model";
+
+/// The tokenizer files found in a model directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TokenizerFiles {
+    /// A Hugging Face `tokenizer.json`.
+    HuggingFace(PathBuf),
+    /// Distinct SentencePiece models for the source and the target side.
+    SentencePiecePair { source: PathBuf, target: PathBuf },
+    /// BPE `vocab.json` and `merges.txt`.
+    Bpe { vocab: PathBuf, merges: PathBuf },
+    /// A single SentencePiece model shared by both sides.
+    SentencePiece(PathBuf),
+}
+
+/// Looks for tokenizer files in the given model directory.
+pub fn detect<P: AsRef<Path>>(model_dir: P) -> Result<TokenizerFiles> {
+    let dir = model_dir.as_ref();
+
+    let tokenizer = dir.join(TOKENIZER_FILE);
+    if tokenizer.is_file() {
+        return Ok(TokenizerFiles::HuggingFace(tokenizer));
+    }
+
+    let (source, target) = (dir.join(SOURCE_SPM_FILE), dir.join(TARGET_SPM_FILE));
+    if source.is_file() && target.is_file() {
+        return Ok(TokenizerFiles::SentencePiecePair { source, target });
+    }
+
+    let (vocab, merges) = (dir.join(VOCAB_FILE), dir.join(MERGES_FILE));
+    if vocab.is_file() && merges.is_file() {
+        return Ok(TokenizerFiles::Bpe { vocab, merges });
+    }
+
+    let mut models = dir
+        .read_dir()
+        .map_err(|err| anyhow!("failed to read {}: {err}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == SPM_EXTENSION))
+        .collect::<Vec<_>>();
+    models.sort();
+    if let Some(model) = models.into_iter().next() {
+        return Ok(TokenizerFiles::SentencePiece(model));
+    }
+
+    Err(anyhow!(
+        "no tokenizer found in {}: expected {TOKENIZER_FILE}, {SOURCE_SPM_FILE} and \
+         {TARGET_SPM_FILE}, {VOCAB_FILE} and {MERGES_FILE}, or a *.{SPM_EXTENSION} file",
+        dir.display()
+    ))
+}
+
+/// Creates the tokenizer matching the files found in the given model directory.
+pub fn load<P: AsRef<Path>>(model_dir: P) -> Result<Box<dyn Tokenizer>> {
+    let dir = model_dir.as_ref();
+    match detect(dir)? {
+        TokenizerFiles::HuggingFace(path) => load_hf(&path),
+        TokenizerFiles::SentencePiecePair { source, target } => {
+            load_spm(&source, &target, special_tokens(dir)?)
+        }
+        TokenizerFiles::Bpe { vocab, merges } => load_bpe(&vocab, &merges),
+        TokenizerFiles::SentencePiece(model) => load_spm(&model, &model, special_tokens(dir)?),
+    }
+}
+
+/// Reads the special tokens of a model directory, without the language tokens.
+fn special_tokens(dir: &Path) -> Result<SpecialTokens> {
+    let mut res = SpecialTokens::from_model_dir(dir)?;
+    res.source_lang = None;
+    res.target_lang = None;
+    Ok(res)
+}
+
+#[cfg(not(all(
+    feature = "tokenizers",
+    any(feature = "rust_tokenizers", feature = "sentencepiece")
+)))]
+fn missing_feature(feature: &str, path: &Path) -> anyhow::Error {
+    anyhow!(
+        "found {}, but loading it requires the {feature} feature of ctranslate2",
+        path.display()
+    )
+}
+
+#[cfg(feature = "tokenizers")]
+fn load_hf(path: &Path) -> Result<Box<dyn Tokenizer>> {
+    Ok(Box::new(super::hf::Tokenizer::from_file(path)?))
+}
+
+#[cfg(not(feature = "tokenizers"))]
+fn load_hf(path: &Path) -> Result<Box<dyn Tokenizer>> {
+    Err(missing_feature("`tokenizers`", path))
+}
+
+#[cfg(feature = "tokenizers")]
+fn load_bpe(vocab: &Path, merges: &Path) -> Result<Box<dyn Tokenizer>> {
+    Ok(Box::new(super::bpe::from_file(vocab, merges, None)?))
+}
+
+#[cfg(not(feature = "tokenizers"))]
+fn load_bpe(vocab: &Path, _merges: &Path) -> Result<Box<dyn Tokenizer>> {
+    Err(missing_feature("`tokenizers`", vocab))
+}
+
+#[cfg(feature = "rust_tokenizers")]
+fn load_spm(
+    source: &Path,
+    target: &Path,
+    special_tokens: SpecialTokens,
+) -> Result<Box<dyn Tokenizer>> {
+    use super::rust_tokenizers::{ModelType, SentenceTokenizer, SentenceTokenizerConfig};
+
+    let load = |path: &Path| -> Result<SentenceTokenizer> {
+        let config = SentenceTokenizerConfig {
+            model_type: ModelType::from_model_file(path)?,
+            ..Default::default()
+        };
+        let mut t = SentenceTokenizer::with_config(path, &config)?;
+        t.with_special_tokens(special_tokens.clone());
+        Ok(t)
+    };
+    if source == target {
+        return Ok(Box::new(load(source)?));
+    }
+    Ok(Box::new(super::TokenizerPair::new(
+        load(source)?,
+        load(target)?,
+    )))
+}
+
+#[cfg(all(feature = "sentencepiece", not(feature = "rust_tokenizers")))]
+fn load_spm(
+    source: &Path,
+    target: &Path,
+    special_tokens: SpecialTokens,
+) -> Result<Box<dyn Tokenizer>> {
+    let mut t = super::sentencepiece::Tokenizer::from_file(source, target)?;
+    t.with_special_tokens(special_tokens);
+    Ok(Box::new(t))
+}
+
+#[cfg(not(any(feature = "sentencepiece", feature = "rust_tokenizers")))]
+fn load_spm(
+    source: &Path,
+    _target: &Path,
+    _special_tokens: SpecialTokens,
+) -> Result<Box<dyn Tokenizer>> {
+    Err(missing_feature(
+        "`rust_tokenizers` or `sentencepiece`",
+        source,
+    ))
+}
+
+#[test]
+fn detect_tokenizer_files() {
+    let dir = std::env::temp_dir().join(format!("ct2-auto-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    assert!(detect(&dir).is_err());
+
+    std::fs::write(dir.join("spm.model"), b"").unwrap();
+    assert_eq!(
+        detect(&dir).unwrap(),
+        TokenizerFiles::SentencePiece(dir.join("spm.model"))
+    );
+
+    std::fs::write(dir.join(SOURCE_SPM_FILE), b"").unwrap();
+    std::fs::write(dir.join(TARGET_SPM_FILE), b"").unwrap();
+    assert!(matches!(
+        detect(&dir).unwrap(),
+        TokenizerFiles::SentencePiecePair { .. }
+    ));
+
+    std::fs::write(
+        dir.join("tokenizer_config.json"),
+        br#"{"eos_token": "<eos>", "src_lang": "eng_Latn"}"#,
+    )
+    .unwrap();
+    let tokens = special_tokens(&dir).unwrap();
+    assert_eq!(tokens.eos_token, "<eos>");
+    assert!(!tokens.has_languages());
+
+    std::fs::write(dir.join(TOKENIZER_FILE), b"{}").unwrap();
+    assert_eq!(
+        detect(&dir).unwrap(),
+        TokenizerFiles::HuggingFace(dir.join(TOKENIZER_FILE))
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -21,43 +21,169 @@
 //! documentation for
 //! [BPEDecoder](https://docs.rs/tokenizers/latest/tokenizers/decoders/bpe/struct.BPEDecoder.html).
 //!
-//! The tokenizer instances created can be utilized in conjunction with structures like
-//! [`Translator`][crate::Translator] and [`Generator`][crate::Generator] for tasks such as
-//! translation or text generation.
+//! [`new`] and [`from_file`] set up the pipeline of BART and RoBERTa models. Other models need
+//! other pre-tokenizers, decoders and special tokens, which [`BpeOptions`] configures, e.g.
+//! [`BpeOptions::gpt2`] for GPT-2 byte-level BPE:
 //!
-//! ## Example
-//! Here is an example of how to create an instance of the Tokenizer
-//! and then use it to create an instance of the [`Generator`][crate::Generator] structure:
+//! ```no_run
+//! # use anyhow::Result;
+//! use ctranslate2::tokenizer::bpe::{self, BpeOptions};
+//!
+//! # fn main() -> Result<()> {
+//! let t = bpe::with_options("/path/to/model", &BpeOptions::gpt2())?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The tokenizers created can be used with [`Translator2`](crate::Translator2):
 //!
 //! ```no_run
 //! # use anyhow::Result;
-//! #
-//! use ct2rs::{Config, Generator};
-//! use ct2rs::tokenizers::bpe;
+//! use ctranslate2::Translator2;
+//! use ctranslate2::tokenizer::bpe;
 //!
 //! # fn main() -> Result<()> {
 //! let path = "/path/to/model";
-//! let t = Generator::with_tokenizer(&path, bpe::new(&path, None)?, &Config::default())?;
+//! let t = Translator2::new(path, &Default::default(), bpe::new(path, None)?)?;
 //! # Ok(())
 //! # }
 //! ```
 
 use std::path::Path;
 
-use anyhow::{Result, anyhow};
 use tokenizers::Tokenizer as HFTokenizer;
 use tokenizers::decoders::bpe::BPEDecoder;
 use tokenizers::models::bpe::BPE;
+use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+use tokenizers::pre_tokenizers::whitespace::Whitespace;
+use tokenizers::processors::bert::BertProcessing;
 use tokenizers::processors::roberta::RobertaProcessing;
+use tokenizers::processors::template::TemplateProcessing;
 
 use crate::tokenizer::hf::Tokenizer;
+use crate::tokenizer::{SpecialTokens, TokenizerError};
 
 const VOCAB_FILE: &str = "vocab.json";
 const MERGES_FILE: &str = "merges.txt";
+const END_OF_WORD_SUFFIX: &str = "</w>";
+const BERT_SEP_TOKEN: &str = "[SEP]";
+const BERT_CLS_TOKEN: &str = "[CLS]";
+
+/// Splits the input into words before the merges are applied.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum PreTokenizer {
+    /// The whole input is a single word.
+    #[default]
+    None,
+    /// Splits on whitespace and punctuation.
+    Whitespace,
+    /// Maps the bytes to printable characters and splits words as GPT-2 does.
+    ByteLevel { add_prefix_space: bool },
+}
+
+/// Turns the tokens back into text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decoder {
+    /// Joins the tokens with spaces.
+    None,
+    /// Joins the tokens and replaces the given end of word suffix with a space.
+    Bpe { suffix: String },
+    /// Maps the byte-level characters back to bytes.
+    ByteLevel,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::Bpe {
+            suffix: END_OF_WORD_SUFFIX.to_owned(),
+        }
+    }
+}
+
+/// Adds special tokens around the tokens of an input.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum PostProcessor {
+    /// No special token is added.
+    #[default]
+    None,
+    /// `<s> ... </s>`, as RoBERTa and BART do. Nothing is added if the vocabulary lacks these
+    /// tokens.
+    Roberta,
+    /// `[CLS] ... [SEP]`, as BERT does. Nothing is added if the vocabulary lacks these tokens.
+    Bert,
+    /// A template of [`TemplateProcessing`], e.g. `<s> $A </s>`, using the given special
+    /// tokens, which must be in the vocabulary.
+    Template {
+        single: String,
+        special_tokens: Vec<String>,
+    },
+}
+
+/// Options of the BPE tokenizer pipeline.
+///
+/// The default options apply the merges to the whole input, decode with the `</w>` suffix and
+/// add no special token.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BpeOptions {
+    pre_tokenizer: PreTokenizer,
+    decoder: Decoder,
+    post_processor: PostProcessor,
+    dropout: Option<f32>,
+    unk_token: Option<String>,
+}
+
+impl BpeOptions {
+    /// The options of BART and RoBERTa models, as used by [`new`] and [`from_file`].
+    pub fn roberta() -> Self {
+        Self::default().with_post_processor(PostProcessor::Roberta)
+    }
+
+    /// The options of GPT-2 byte-level BPE models.
+    pub fn gpt2() -> Self {
+        Self::default()
+            .with_pre_tokenizer(PreTokenizer::ByteLevel {
+                add_prefix_space: false,
+            })
+            .with_decoder(Decoder::ByteLevel)
+    }
+
+    /// Sets the pre-tokenizer.
+    pub fn with_pre_tokenizer(mut self, pre_tokenizer: PreTokenizer) -> Self {
+        self.pre_tokenizer = pre_tokenizer;
+        self
+    }
+
+    /// Sets the decoder.
+    pub fn with_decoder(mut self, decoder: Decoder) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Sets the post-processor.
+    pub fn with_post_processor(mut self, post_processor: PostProcessor) -> Self {
+        self.post_processor = post_processor;
+        self
+    }
+
+    /// Skips each merge with the given probability (BPE-dropout). Only useful for training.
+    pub fn with_dropout(mut self, dropout: f32) -> Self {
+        self.dropout = Some(dropout);
+        self
+    }
+
+    /// Sets the token replacing the characters missing from the vocabulary.
+    pub fn with_unk_token<S: Into<String>>(mut self, unk_token: S) -> Self {
+        self.unk_token = Some(unk_token.into());
+        self
+    }
+}
 
 /// Create a tokenizer instance by specifying the path to a directory containing `vocab.json`
 /// and `mergers.txt`.
-pub fn new<T: AsRef<Path>>(path: T, decoder_suffix: Option<String>) -> Result<Tokenizer> {
+pub fn new<T: AsRef<Path>>(
+    path: T,
+    decoder_suffix: Option<String>,
+) -> Result<Tokenizer, TokenizerError> {
     from_file(
         path.as_ref().join(VOCAB_FILE),
         path.as_ref().join(MERGES_FILE),
@@ -70,29 +196,163 @@ pub fn from_file<T: AsRef<Path>, U: AsRef<Path>>(
     vocab: T,
     merges: U,
     decoder_suffix: Option<String>,
-) -> Result<Tokenizer> {
+) -> Result<Tokenizer, TokenizerError> {
+    let mut options = BpeOptions::roberta();
+    if let Some(suffix) = decoder_suffix {
+        options = options.with_decoder(Decoder::Bpe { suffix });
+    }
+    from_file_with_options(vocab, merges, &options)
+}
+
+/// Create a tokenizer instance with the given options by specifying the path to a directory
+/// containing `vocab.json` and `mergers.txt`.
+pub fn with_options<T: AsRef<Path>>(
+    path: T,
+    options: &BpeOptions,
+) -> Result<Tokenizer, TokenizerError> {
+    from_file_with_options(
+        path.as_ref().join(VOCAB_FILE),
+        path.as_ref().join(MERGES_FILE),
+        options,
+    )
+}
+
+/// Create a tokenizer instance with the given options by specifying the path to `vocab.json`
+/// and `mergers.txt`.
+pub fn from_file_with_options<T: AsRef<Path>, U: AsRef<Path>>(
+    vocab: T,
+    merges: U,
+    options: &BpeOptions,
+) -> Result<Tokenizer, TokenizerError> {
+    let (vocab, merges) = (vocab.as_ref(), merges.as_ref());
+    TokenizerError::check_file(vocab)?;
+    TokenizerError::check_file(merges)?;
+
+    let mut builder = BPE::from_file(
+        vocab
+            .to_str()
+            .ok_or_else(|| TokenizerError::parse(vocab, "invalid path"))?,
+        merges
+            .to_str()
+            .ok_or_else(|| TokenizerError::parse(merges, "invalid path"))?,
+    );
+    if let Some(dropout) = options.dropout {
+        builder = builder.dropout(dropout);
+    }
+    if let Some(unk_token) = &options.unk_token {
+        builder = builder.unk_token(unk_token.clone());
+    }
     let mut res = Tokenizer::from(HFTokenizer::new(
-        BPE::from_file(
-            vocab
-                .as_ref()
-                .to_str()
-                .ok_or_else(|| anyhow!("invalid path: {}", vocab.as_ref().display()))?,
-            merges
-                .as_ref()
-                .to_str()
-                .ok_or_else(|| anyhow!("invalid path: {}", merges.as_ref().display()))?,
-        )
-        .build()
-        .map_err(|err| anyhow!("failed to build a tokenizer: {err}"))?,
+        builder
+            .build()
+            .map_err(|err| TokenizerError::parse(vocab, err))?,
     ));
 
-    res.with_decoder(Some(match decoder_suffix {
-        None => BPEDecoder::default(),
-        Some(s) => BPEDecoder::new(s),
-    }))
-    .with_post_processor(Some(
-        RobertaProcessing::new(("</s>".to_string(), 2), ("<s>".to_string(), 0)).trim_offsets(true),
-    ));
+    match options.pre_tokenizer {
+        PreTokenizer::None => {}
+        PreTokenizer::Whitespace => {
+            res.with_pre_tokenizer(Some(Whitespace));
+        }
+        PreTokenizer::ByteLevel { add_prefix_space } => {
+            res.with_pre_tokenizer(Some(
+                ByteLevel::default().add_prefix_space(add_prefix_space),
+            ));
+        }
+    }
+
+    match &options.decoder {
+        Decoder::None => {}
+        Decoder::Bpe { suffix } => {
+            res.with_decoder(Some(BPEDecoder::new(suffix.clone())));
+        }
+        Decoder::ByteLevel => {
+            res.with_decoder(Some(ByteLevel::default()));
+        }
+    }
+
+    let token = |token: &str| res.token_to_id(token).map(|id| (token.to_owned(), id));
+    match &options.post_processor {
+        PostProcessor::None => {}
+        PostProcessor::Roberta => {
+            let special = SpecialTokens::default();
+            if let (Some(eos), Some(bos)) = (token(&special.eos_token), token(&special.bos_token)) {
+                let add_prefix_space = matches!(
+                    options.pre_tokenizer,
+                    PreTokenizer::ByteLevel {
+                        add_prefix_space: true
+                    }
+                );
+                res.with_post_processor(Some(
+                    RobertaProcessing::new(eos, bos)
+                        .trim_offsets(true)
+                        .add_prefix_space(add_prefix_space),
+                ));
+            }
+        }
+        PostProcessor::Bert => {
+            if let (Some(sep), Some(cls)) = (token(BERT_SEP_TOKEN), token(BERT_CLS_TOKEN)) {
+                res.with_post_processor(Some(BertProcessing::new(sep, cls)));
+            }
+        }
+        PostProcessor::Template {
+            single,
+            special_tokens,
+        } => {
+            let special_tokens = special_tokens
+                .iter()
+                .map(|t| {
+                    token(t).ok_or_else(|| {
+                        TokenizerError::parse(vocab, format!("special token not found: {t}"))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let processor = TemplateProcessing::builder()
+                .try_single(single.as_str())
+                .map_err(|err| TokenizerError::parse(vocab, err))?
+                .special_tokens(special_tokens)
+                .build()
+                .map_err(|err| TokenizerError::parse(vocab, err))?;
+            res.with_post_processor(Some(processor));
+        }
+    }
 
     Ok(res)
 }
+
+#[test]
+fn bpe_options() {
+    use crate::Tokenizer as _;
+
+    let dir = std::env::temp_dir().join(format!("ct2-bpe-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join(VOCAB_FILE),
+        r#"{"<s>": 0, "</s>": 1, "[CLS]": 2, "[SEP]": 3, "a": 4, "b": 5, "ab": 6}"#,
+    )
+    .unwrap();
+    std::fs::write(dir.join(MERGES_FILE), "#version: 0.2\na b\n").unwrap();
+
+    let t = new(&dir, None).unwrap();
+    assert_eq!(t.encode("ab").unwrap(), ["<s>", "ab", "</s>"]);
+
+    let options = BpeOptions::default().with_post_processor(PostProcessor::Bert);
+    let t = with_options(&dir, &options).unwrap();
+    assert_eq!(t.encode("ab").unwrap(), ["[CLS]", "ab", "[SEP]"]);
+
+    let t = with_options(&dir, &BpeOptions::default()).unwrap();
+    assert_eq!(t.encode("ab").unwrap(), ["ab"]);
+
+    let options = BpeOptions::default().with_post_processor(PostProcessor::Template {
+        single: "$A </s>".to_owned(),
+        special_tokens: vec!["</s>".to_owned()],
+    });
+    let t = with_options(&dir, &options).unwrap();
+    assert_eq!(t.encode("ab").unwrap(), ["ab", "</s>"]);
+
+    let options = BpeOptions::default().with_post_processor(PostProcessor::Template {
+        single: "$A <eos>".to_owned(),
+        special_tokens: vec!["<eos>".to_owned()],
+    });
+    assert!(with_options(&dir, &options).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,123 @@
+//! BPE merge operations in the `codes` format of subword-nmt and the OpenNMT Tokenizer.
+//!
+//! A codes file lists one merge per line, most frequent first, optionally preceded by a
+//! `#version: 0.2` header:
+//!
+//! ```text
+//! #version: 0.2
+//! l o
+//! lo w</w>
+//! ```
+//!
+//! In version 0.2, the end of word marker `</w>` is part of the last character of a word; in
+//! version 0.1, it is a symbol of its own.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::tokenizer::TokenizerError;
+
+const END_OF_WORD: &str = "</w>";
+const VERSION_HEADER: &str = "#version:";
+
+/// BPE merge operations, applied to single words.
+#[derive(Clone, Debug, Default)]
+pub struct BpeCodes {
+    ranks: HashMap<(String, String), usize>,
+    separate_end_of_word: bool,
+}
+
+impl BpeCodes {
+    /// Reads a codes file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, TokenizerError> {
+        let path = path.as_ref();
+        TokenizerError::check_file(path)?;
+        let content = fs::read_to_string(path).map_err(|err| TokenizerError::parse(path, err))?;
+        Self::parse(&content).map_err(|err| TokenizerError::parse(path, err))
+    }
+
+    /// Parses the content of a codes file.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut res = Self::default();
+        for (idx, line) in content.lines().enumerate() {
+            if let Some(version) = line.strip_prefix(VERSION_HEADER) {
+                res.separate_end_of_word = version.trim() == "0.1";
+                continue;
+            }
+            let mut pair = line.split_whitespace();
+            match (pair.next(), pair.next()) {
+                (Some(left), Some(right)) => {
+                    let rank = res.ranks.len();
+                    res.ranks
+                        .entry((left.to_owned(), right.to_owned()))
+                        .or_insert(rank);
+                }
+                (None, _) => continue,
+                _ => return Err(format!("invalid merge at line {}: {line}", idx + 1)),
+            }
+        }
+        Ok(res)
+    }
+
+    /// Returns the number of merge operations.
+    pub fn len(&self) -> usize {
+        self.ranks.len()
+    }
+
+    /// Returns whether there is no merge operation.
+    pub fn is_empty(&self) -> bool {
+        self.ranks.is_empty()
+    }
+
+    /// Splits a word into subwords by applying the merge operations, lowest rank first.
+    pub fn apply(&self, word: &str) -> Vec<String> {
+        let mut symbols = word.chars().map(String::from).collect::<Vec<_>>();
+        match symbols.last_mut() {
+            None => return symbols,
+            Some(_) if self.separate_end_of_word => symbols.push(END_OF_WORD.to_owned()),
+            Some(last) => last.push_str(END_OF_WORD),
+        }
+
+        while let Some(best) = symbols
+            .windows(2)
+            .filter_map(|w| self.ranks.get(&(w[0].clone(), w[1].clone())))
+            .min()
+            .copied()
+        {
+            let mut merged = Vec::with_capacity(symbols.len());
+            let mut iter = symbols.into_iter().peekable();
+            while let Some(symbol) = iter.next() {
+                match iter.peek() {
+                    Some(next)
+                        if self.ranks.get(&(symbol.clone(), next.clone())) == Some(&best) =>
+                    {
+                        let next = iter.next().unwrap_or_default();
+                        merged.push(symbol + &next);
+                    }
+                    _ => merged.push(symbol),
+                }
+            }
+            symbols = merged;
+        }
+
+        if symbols.last().is_some_and(|s| s == END_OF_WORD) {
+            symbols.pop();
+        } else if let Some(last) = symbols.last_mut()
+            && let Some(len) = last.strip_suffix(END_OF_WORD).map(str::len)
+        {
+            last.truncate(len);
+        }
+        symbols
+    }
+}
+
+#[test]
+fn apply_bpe_codes() {
+    let codes = BpeCodes::parse("#version: 0.2\nl o\nlo w</w>\ne r</w>\n").unwrap();
+    assert_eq!(codes.len(), 3);
+    assert_eq!(codes.apply("low"), ["low"]);
+    assert_eq!(codes.apply("lower"), ["lo", "w", "er"]);
+    assert_eq!(codes.apply("a"), ["a"]);
+    assert!(BpeCodes::parse("l o\nlow\n").is_err());
+}
@@ -11,21 +11,16 @@
 //!
 //! This module allows the creation of a [`Tokenizer`] structure instance by specifying
 //! the path to a directory containing `tokenizer.json`. The created instance can then be
-//! used in [`Translator`](crate::Translator) and [`Generator`](crate::Generator).
-//!
-//! # Example
-//! Here is an example of how to create an instance of the [`Tokenizer`] structure
-//! and then use it to create an instance of the [`Translator`](crate::Translator):
+//! used in [`Translator2`](crate::Translator2):
 //!
 //! ```no_run
 //! # use anyhow::Result;
-//! #
-//! use ct2rs::{Config, Translator};
-//! use ct2rs::tokenizers::hf::Tokenizer;
+//! use ctranslate2::Translator2;
+//! use ctranslate2::tokenizer::hf::Tokenizer;
 //!
 //! # fn main() -> Result<()> {
 //! let path = "/path/to/model";
-//! let t = Translator::with_tokenizer(&path, Tokenizer::new(&path)?, &Config::default())?;
+//! let t = Translator2::new(path, &Default::default(), Tokenizer::new(path)?)?;
 //! # Ok(())
 //! # }
 //! ```
@@ -34,7 +29,11 @@ use std::ops::{Deref, DerefMut};
 use std::path::Path;
 
 use anyhow::{Result, anyhow};
-use tokenizers::Decoder;
+use tokenizers::Decoder as _;
+use tokenizers::models::ModelWrapper;
+
+use crate::tokenizer::streaming::IncrementalDecoder;
+use crate::tokenizer::{SpecialTokens, StreamingDecoder, TokenizerError};
 
 const TOKENIZER_FILENAME: &str = "tokenizer.json";
 
@@ -43,21 +42,27 @@ pub struct Tokenizer {
     tokenizer: tokenizers::Tokenizer,
 
     special_token: bool,
+    skip_special_tokens: bool,
+    special_tokens: Option<SpecialTokens>,
 }
 
 impl Tokenizer {
     /// Create a tokenizer instance by specifying the path to a directory containing
     /// `tokenizer.json`.
-    pub fn new<T: AsRef<Path>>(path: T) -> Result<Self> {
+    pub fn new<T: AsRef<Path>>(path: T) -> Result<Self, TokenizerError> {
         Tokenizer::from_file(path.as_ref().join(TOKENIZER_FILENAME))
     }
 
     /// Create a tokenizer instance by specifying the path to `tokenizer.json`.
-    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<Self> {
+    pub fn from_file<T: AsRef<Path>>(path: T) -> Result<Self, TokenizerError> {
+        let path = path.as_ref();
+        TokenizerError::check_file(path)?;
         Ok(Self {
             tokenizer: tokenizers::tokenizer::Tokenizer::from_file(path)
-                .map_err(|err| anyhow!("failed to load a tokenizer: {err}"))?,
+                .map_err(|err| TokenizerError::parse(path, err))?,
             special_token: true,
+            skip_special_tokens: true,
+            special_tokens: None,
         })
     }
 
@@ -69,6 +74,68 @@ impl Tokenizer {
         self
     }
 
+    /// Sets whether decoding removes the special tokens of `tokenizer.json`, `true` by default.
+    pub fn with_skip_special_tokens(&mut self, skip: bool) -> &mut Self {
+        self.skip_special_tokens = skip;
+        self
+    }
+
+    /// Sets the special tokens added when encoding and removed when decoding.
+    ///
+    /// They replace the special tokens inserted by the post-processor of `tokenizer.json`.
+    pub fn with_special_tokens(&mut self, special_tokens: SpecialTokens) -> &mut Self {
+        self.special_tokens = Some(special_tokens);
+        self
+    }
+
+    fn tokenize(&self, input: &str, add_special_tokens: bool) -> Result<Vec<String>> {
+        self.tokenizer
+            .encode(input, add_special_tokens)
+            .map(|r| r.get_tokens().to_vec())
+            .map_err(|err| anyhow!("failed to encode the given input: {err}"))
+    }
+
+    /// Frames the tokens of an encoding with the configured special tokens, if any.
+    fn frame(&self, tokens: Vec<String>) -> Vec<String> {
+        match &self.special_tokens {
+            Some(special_tokens) => special_tokens.apply_source(tokens),
+            None => tokens,
+        }
+    }
+
+    /// Returns whether the post-processor of `tokenizer.json` adds the special tokens.
+    fn add_special_tokens(&self) -> bool {
+        self.special_tokens.is_none() && self.special_token
+    }
+
+    /// Returns the id of the unknown token of the model, `<unk>` for unigram models.
+    fn unk_id(&self) -> Option<u32> {
+        let unk = match self.tokenizer.get_model() {
+            ModelWrapper::BPE(bpe) => bpe.unk_token.clone(),
+            ModelWrapper::WordPiece(wordpiece) => Some(wordpiece.unk_token.clone()),
+            ModelWrapper::WordLevel(wordlevel) => Some(wordlevel.unk_token.clone()),
+            ModelWrapper::Unigram(_) => None,
+        };
+        self.tokenizer
+            .token_to_id(unk.as_deref().unwrap_or("<unk>"))
+    }
+
+    /// Decodes tokens with the decoder of `tokenizer.json` only, or joins them with spaces if
+    /// it has no decoder. Used for tokens missing from the vocabulary, which have no id.
+    fn decode_tokens(&self, tokens: Vec<String>) -> Result<String> {
+        let added = self.tokenizer.get_added_vocabulary();
+        let tokens = tokens
+            .into_iter()
+            .filter(|t| !(self.skip_special_tokens && added.is_special_token(t)))
+            .collect::<Vec<_>>();
+        match self.tokenizer.get_decoder() {
+            Some(decoder) => decoder
+                .decode(tokens)
+                .map_err(|err| anyhow!("failed to decode: {err}")),
+            None => Ok(tokens.join(" ")),
+        }
+    }
+
     /// Returns a mutable reference of the `tokenizers::Tokenizer`.
     #[inline]
     pub fn inner(&mut self) -> &mut tokenizers::Tokenizer {
@@ -89,15 +156,25 @@ impl crate::Tokenizer for Tokenizer {
     /// A `Result` containing either the vector of tokens if successful or an error if the
     /// tokenization fails.
     fn encode(&self, input: &str) -> Result<Vec<String>> {
-        self.tokenizer
-            .encode(input, self.special_token)
-            .map(|r| r.get_tokens().to_vec())
-            .map_err(|err| anyhow!("failed to encode the given input: {err}"))
+        Ok(self.frame(self.tokenize(input, self.add_special_tokens())?))
+    }
+
+    /// Encodes a target prefix. Only the target language token of the configured
+    /// [`SpecialTokens`] is added.
+    fn encode_prefix(&self, input: &str) -> Result<Vec<String>> {
+        let tokens = self.tokenize(input, false)?;
+        Ok(match &self.special_tokens {
+            Some(special_tokens) => special_tokens.apply_target_prefix(tokens),
+            None => tokens,
+        })
     }
 
     /// Decodes a given sequence of tokens back into a single string.
     ///
-    /// This function takes a vector of token strings and reconstructs the original string.
+    /// This function takes a vector of token strings and reconstructs the original string
+    /// with the full decoding pipeline of `tokenizer.json`. The tokens are joined with spaces
+    /// if it has no decoder. If some tokens are not in the vocabulary, only the decoder is
+    /// applied to the token strings.
     ///
     /// # Arguments
     /// * `tokens` - A vector of strings representing the tokens to be decoded.
@@ -106,15 +183,89 @@ impl crate::Tokenizer for Tokenizer {
     /// A `Result` containing either the reconstructed string if successful or an error if the
     /// decoding fails.
     fn decode(&self, tokens: Vec<String>) -> Result<String> {
-        let decoder = self
-            .tokenizer
-            .get_decoder()
-            .ok_or_else(|| anyhow!("no decoder is provided"))?;
+        let tokens = match &self.special_tokens {
+            Some(special_tokens) => special_tokens.strip(tokens),
+            None => tokens,
+        };
+        let Some(ids) = tokens
+            .iter()
+            .map(|token| self.tokenizer.token_to_id(token))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return self.decode_tokens(tokens);
+        };
 
-        decoder
-            .decode(tokens)
+        self.tokenizer
+            .decode(&ids, self.skip_special_tokens)
             .map_err(|err| anyhow!("failed to decode: {err}"))
     }
+
+    /// Encodes a batch of strings in parallel.
+    fn encode_batch(&self, inputs: &[&str]) -> Result<Vec<Vec<String>>> {
+        Ok(self
+            .tokenizer
+            .encode_batch(inputs.to_vec(), self.add_special_tokens())
+            .map_err(|err| anyhow!("failed to encode the given input: {err}"))?
+            .into_iter()
+            .map(|encoding| self.frame(encoding.get_tokens().to_vec()))
+            .collect())
+    }
+
+    /// Encodes a string into token ids. Tokens not in the vocabulary, e.g. special tokens
+    /// added by [`SpecialTokens`], get the id of the unknown token.
+    fn encode_ids(&self, input: &str) -> Result<Vec<u32>> {
+        self.encode(input)?
+            .iter()
+            .map(|token| {
+                self.tokenizer
+                    .token_to_id(token)
+                    .or_else(|| self.unk_id())
+                    .ok_or_else(|| anyhow!("token not in the vocabulary: {token}"))
+            })
+            .collect()
+    }
+
+    fn decode_ids(&self, ids: &[u32]) -> Result<String> {
+        let tokens = ids
+            .iter()
+            .map(|&id| {
+                self.tokenizer
+                    .id_to_token(id)
+                    .ok_or_else(|| anyhow!("id not in the vocabulary: {id}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        crate::Tokenizer::decode(self, tokens)
+    }
+
+    /// Returns an [`IncrementalDecoder`], so the full decoding pipeline of `tokenizer.json` is
+    /// applied. Characters split across byte-level BPE tokens are held back until complete.
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        Box::new(IncrementalDecoder::new(self))
+    }
+
+    /// Encodes a given string, keeping the byte offsets computed by the `tokenizers` crate.
+    fn encode_with_offsets(&self, input: &str) -> Result<Vec<(String, (usize, usize))>> {
+        let encoding = self
+            .tokenizer
+            .encode(input, self.add_special_tokens())
+            .map_err(|err| anyhow!("failed to encode the given input: {err}"))?;
+        let tokens = encoding
+            .get_tokens()
+            .iter()
+            .cloned()
+            .zip(encoding.get_offsets().iter().copied())
+            .collect();
+        Ok(match &self.special_tokens {
+            Some(special_tokens) => special_tokens.apply_source_with_offsets(tokens, input.len()),
+            None => tokens,
+        })
+    }
+
+    fn adds_language_tokens(&self) -> bool {
+        self.special_tokens
+            .as_ref()
+            .is_some_and(SpecialTokens::has_languages)
+    }
 }
 
 impl From<tokenizers::Tokenizer> for Tokenizer {
@@ -122,6 +273,8 @@ impl From<tokenizers::Tokenizer> for Tokenizer {
         Self {
             tokenizer,
             special_token: true,
+            skip_special_tokens: true,
+            special_tokens: None,
         }
     }
 }
@@ -141,3 +294,41 @@ impl DerefMut for Tokenizer {
         &mut self.tokenizer
     }
 }
+
+#[test]
+fn decode_without_decoder() {
+    use crate::Tokenizer as _;
+
+    let mut t = Tokenizer::from(
+        r#"{
+            "version": "1.0",
+            "added_tokens": [{
+                "id": 2, "content": "</s>", "single_word": false, "lstrip": false,
+                "rstrip": false, "normalized": false, "special": true
+            }],
+            "normalizer": null,
+            "pre_tokenizer": {"type": "WhitespaceSplit"},
+            "post_processor": null,
+            "decoder": null,
+            "model": {"type": "WordLevel", "vocab": {"hello": 0, "world": 1, "</s>": 2}, "unk_token": "</s>"}
+        }"#
+        .parse::<tokenizers::Tokenizer>()
+        .unwrap(),
+    );
+    let tokens = vec!["hello".to_owned(), "world".to_owned(), "</s>".to_owned()];
+    assert_eq!(t.decode(tokens.clone()).unwrap(), "hello world");
+
+    t.with_skip_special_tokens(false);
+    assert_eq!(t.decode(tokens).unwrap(), "hello world </s>");
+
+    let tokens = vec!["hello".to_owned(), "mundo".to_owned(), "</s>".to_owned()];
+    assert_eq!(t.decode(tokens.clone()).unwrap(), "hello mundo </s>");
+    t.with_skip_special_tokens(true);
+    assert_eq!(t.decode(tokens).unwrap(), "hello mundo");
+
+    t.with_special_tokens(SpecialTokens {
+        source_lang: Some("eng_Latn".to_owned()),
+        ..Default::default()
+    });
+    assert_eq!(t.encode_ids("hello world").unwrap(), [2, 0, 1, 2]);
+}
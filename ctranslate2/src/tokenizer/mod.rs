@@ -1,16 +1,210 @@
+pub mod auto;
 #[cfg(feature = "tokenizers")]
 pub mod bpe;
+pub mod bpe_codes;
 #[cfg(feature = "tokenizers")]
 pub mod hf;
+pub mod moses;
+pub mod onmt;
+pub mod pair;
 #[cfg(feature = "rust_tokenizers")]
 pub mod rust_tokenizers;
 #[cfg(feature = "sentencepiece")]
 pub mod sentencepiece;
+pub mod special_tokens;
+pub mod streaming;
+pub mod subword_nmt;
+pub mod whitespace;
 
-pub trait Tokenizer {
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub use pair::TokenizerPair;
+pub use special_tokens::{LanguageTagPosition, SpecialTokens};
+pub use streaming::StreamingDecoder;
+
+/// Errors returned when creating a tokenizer.
+#[derive(Debug)]
+pub enum TokenizerError {
+    /// The model file does not exist.
+    FileNotFound(PathBuf),
+    /// The model file exists but could not be loaded.
+    Parse { path: PathBuf, message: String },
+    /// The requested model type is not supported by the backend.
+    UnsupportedModelType(String),
+}
+
+#[cfg_attr(
+    not(any(
+        feature = "tokenizers",
+        feature = "rust_tokenizers",
+        feature = "sentencepiece"
+    )),
+    allow(dead_code)
+)]
+impl TokenizerError {
+    /// Returns [`TokenizerError::FileNotFound`] unless `path` is an existing file.
+    pub(crate) fn check_file(path: &Path) -> Result<(), TokenizerError> {
+        if path.is_file() {
+            Ok(())
+        } else {
+            Err(TokenizerError::FileNotFound(path.to_path_buf()))
+        }
+    }
+
+    pub(crate) fn parse<E: fmt::Display>(path: &Path, err: E) -> TokenizerError {
+        TokenizerError::Parse {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for TokenizerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizerError::FileNotFound(path) => {
+                write!(f, "Tokenizer file not found: {}", path.display())
+            }
+            TokenizerError::Parse { path, message } => {
+                write!(
+                    f,
+                    "Failed to load tokenizer {}: {}",
+                    path.display(),
+                    message
+                )
+            }
+            TokenizerError::UnsupportedModelType(model_type) => {
+                write!(f, "Unsupported tokenizer model type: {}", model_type)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TokenizerError {}
+
+/// Converts text into the tokens fed to the model and back.
+///
+/// Only [`encode`](Tokenizer::encode) and [`decode`](Tokenizer::decode) are required. The batch
+/// methods loop over the items by default and can be overridden with parallel
+/// implementations. The id and offset methods return an error unless the tokenizer supports
+/// them.
+pub trait Tokenizer: Send + Sync {
     /// Encodes a given string into a sequence of tokens
     fn encode(&self, input: &str) -> anyhow::Result<Vec<String>>;
 
     /// Decodes a given sequence of tokens back into a single string
     fn decode(&self, tokens: Vec<String>) -> anyhow::Result<String>;
+
+    /// Encodes a target prefix. Unlike [`encode`](Tokenizer::encode), the result must not end
+    /// with an end of sentence token, as the model would stop decoding right after it.
+    ///
+    /// The default implementation removes a trailing `</s>` from the output of `encode`.
+    fn encode_prefix(&self, input: &str) -> anyhow::Result<Vec<String>> {
+        let mut tokens = self.encode(input)?;
+        if tokens.last().is_some_and(|t| t == "</s>") {
+            tokens.pop();
+        }
+        Ok(tokens)
+    }
+
+    /// Encodes a batch of strings.
+    fn encode_batch(&self, inputs: &[&str]) -> anyhow::Result<Vec<Vec<String>>> {
+        inputs.iter().map(|input| self.encode(input)).collect()
+    }
+
+    /// Decodes a batch of token sequences.
+    fn decode_batch(&self, tokens: Vec<Vec<String>>) -> anyhow::Result<Vec<String>> {
+        tokens.into_iter().map(|t| self.decode(t)).collect()
+    }
+
+    /// Encodes a given string into a sequence of vocabulary ids.
+    fn encode_ids(&self, _input: &str) -> anyhow::Result<Vec<u32>> {
+        Err(anyhow::anyhow!("this tokenizer does not support ids"))
+    }
+
+    /// Decodes a given sequence of vocabulary ids back into a single string.
+    fn decode_ids(&self, _ids: &[u32]) -> anyhow::Result<String> {
+        Err(anyhow::anyhow!("this tokenizer does not support ids"))
+    }
+
+    /// Encodes a given string into a sequence of tokens, each with the `(start, end)` byte span
+    /// of the input it comes from. Special tokens not taken from the input have an empty span.
+    fn encode_with_offsets(&self, _input: &str) -> anyhow::Result<Vec<(String, (usize, usize))>> {
+        Err(anyhow::anyhow!("this tokenizer does not support offsets"))
+    }
+
+    /// Returns a decoder receiving the target tokens one at a time.
+    ///
+    /// The default implementation decodes the whole sequence again for every token, see
+    /// [`streaming::IncrementalDecoder`].
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        Box::new(streaming::IncrementalDecoder::new(self))
+    }
+
+    /// Returns whether the tokenizer adds language tokens by itself, i.e. it is configured
+    /// with a [`SpecialTokens::source_lang`] or [`SpecialTokens::target_lang`].
+    fn adds_language_tokens(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Tokenizer + ?Sized> Tokenizer for Box<T> {
+    fn encode(&self, input: &str) -> anyhow::Result<Vec<String>> {
+        (**self).encode(input)
+    }
+
+    fn decode(&self, tokens: Vec<String>) -> anyhow::Result<String> {
+        (**self).decode(tokens)
+    }
+
+    fn encode_prefix(&self, input: &str) -> anyhow::Result<Vec<String>> {
+        (**self).encode_prefix(input)
+    }
+
+    fn encode_batch(&self, inputs: &[&str]) -> anyhow::Result<Vec<Vec<String>>> {
+        (**self).encode_batch(inputs)
+    }
+
+    fn decode_batch(&self, tokens: Vec<Vec<String>>) -> anyhow::Result<Vec<String>> {
+        (**self).decode_batch(tokens)
+    }
+
+    fn encode_ids(&self, input: &str) -> anyhow::Result<Vec<u32>> {
+        (**self).encode_ids(input)
+    }
+
+    fn decode_ids(&self, ids: &[u32]) -> anyhow::Result<String> {
+        (**self).decode_ids(ids)
+    }
+
+    fn encode_with_offsets(&self, input: &str) -> anyhow::Result<Vec<(String, (usize, usize))>> {
+        (**self).encode_with_offsets(input)
+    }
+
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        (**self).streaming_decoder()
+    }
+
+    fn adds_language_tokens(&self) -> bool {
+        (**self).adds_language_tokens()
+    }
+}
+
+#[test]
+fn provided_tokenizer_methods() {
+    fn shareable<T: Send + Sync>(_: &T) {}
+
+    let t: Box<dyn Tokenizer> = Box::new(whitespace::Tokenizer::new());
+    shareable(&t);
+    assert_eq!(
+        t.encode_batch(&["a b", "c"]).unwrap(),
+        vec![vec!["a", "b", "</s>"], vec!["c", "</s>"]]
+    );
+    assert_eq!(
+        t.decode_batch(vec![vec!["a".to_owned(), "</s>".to_owned()]])
+            .unwrap(),
+        ["a"]
+    );
+    assert!(t.encode_ids("a").is_err());
 }
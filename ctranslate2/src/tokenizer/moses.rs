@@ -0,0 +1,645 @@
+//! A pure-Rust port of the Moses preprocessing scripts: `normalize-punctuation.perl`,
+//! `tokenizer.perl` and `detokenizer.perl`.
+//!
+//! Sentence-final periods are split from the last word unless the word is a known
+//! abbreviation. The nonbreaking prefixes of English, German and French are built in; other
+//! languages can load a Moses `nonbreaking_prefix.<lang>` file with
+//! [`Tokenizer::with_nonbreaking_prefix_file`].
+//!
+//! ```
+//! use ctranslate2::tokenizer::moses::Tokenizer;
+//!
+//! let t = Tokenizer::new("en");
+//! let tokens = t.tokenize("Mr. Smith isn't here, is he?");
+//! assert_eq!(tokens.join(" "), "Mr. Smith isn &apos;t here , is he ?");
+//! assert_eq!(t.detokenize(&tokens), "Mr. Smith isn't here, is he?");
+//! ```
+//!
+//! Combine it with BPE using [`subword_nmt::Tokenizer`](crate::tokenizer::subword_nmt::Tokenizer).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::tokenizer::{SpecialTokens, StreamingDecoder, TokenizerError};
+
+const NONBREAKING_PREFIX_EN: &str = include_str!("../../data/moses/nonbreaking_prefix.en");
+const NONBREAKING_PREFIX_DE: &str = include_str!("../../data/moses/nonbreaking_prefix.de");
+const NONBREAKING_PREFIX_FR: &str = include_str!("../../data/moses/nonbreaking_prefix.fr");
+const NUMERIC_ONLY: &str = "#NUMERIC_ONLY#";
+const DASH: &str = "@-@";
+
+const ESCAPES: [(char, &str); 8] = [
+    ('&', "&amp;"),
+    ('|', "&#124;"),
+    ('<', "&lt;"),
+    ('>', "&gt;"),
+    ('\'', "&apos;"),
+    ('"', "&quot;"),
+    ('[', "&#91;"),
+    (']', "&#93;"),
+];
+
+const CURRENCIES: &str = "$£€¥¢₩₹₽¤";
+
+/// Options of the Moses tokenizer.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Normalize the punctuation before tokenizing, as `normalize-punctuation.perl`.
+    pub normalize_punctuation: bool,
+    /// Escape the characters special to Moses, e.g. `&` as `&amp;`.
+    pub escape: bool,
+    /// Split hyphens between letters or digits into `@-@` tokens (`-a`).
+    pub aggressive_dash_splits: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            normalize_punctuation: true,
+            escape: true,
+            aggressive_dash_splits: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Prefix {
+    /// Never ends a sentence.
+    Always,
+    /// Does not end a sentence when followed by a number.
+    NumericOnly,
+}
+
+/// The Moses tokenizer of one language.
+#[derive(Clone, Debug)]
+pub struct Tokenizer {
+    lang: String,
+    options: Options,
+    prefixes: HashMap<String, Prefix>,
+    special_tokens: SpecialTokens,
+}
+
+impl Tokenizer {
+    /// Create a tokenizer for the given language code, e.g. `en`.
+    pub fn new(lang: &str) -> Self {
+        let mut prefixes = HashMap::new();
+        parse_prefixes(builtin_prefixes(lang), &mut prefixes);
+        Self {
+            lang: lang.to_owned(),
+            options: Options::default(),
+            prefixes,
+            special_tokens: SpecialTokens::default(),
+        }
+    }
+
+    /// Sets the options.
+    pub fn with_options(&mut self, options: Options) -> &mut Self {
+        self.options = options;
+        self
+    }
+
+    /// Adds the nonbreaking prefixes of a Moses `nonbreaking_prefix.<lang>` file.
+    pub fn with_nonbreaking_prefix_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<&mut Self, TokenizerError> {
+        let path = path.as_ref();
+        TokenizerError::check_file(path)?;
+        let content = fs::read_to_string(path).map_err(|err| TokenizerError::parse(path, err))?;
+        parse_prefixes(&content, &mut self.prefixes);
+        Ok(self)
+    }
+
+    /// Sets the special tokens added when encoding and removed when decoding.
+    pub fn with_special_tokens(&mut self, special_tokens: SpecialTokens) -> &mut Self {
+        self.special_tokens = special_tokens;
+        self
+    }
+
+    /// Returns the language code.
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    /// Normalizes quotes, dashes and the spaces around punctuation.
+    pub fn normalize(&self, text: &str) -> String {
+        self.normalize_spanned(text)
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect()
+    }
+
+    /// Normalizes a text like [`normalize`](Self::normalize), keeping the span of every
+    /// character.
+    fn normalize_spanned(&self, text: &str) -> Spanned {
+        let chars = spanned(text)
+            .into_iter()
+            .filter(|&(c, _)| c != '\r')
+            .collect::<Vec<_>>();
+        let mut res = Spanned::with_capacity(text.len());
+        for (i, &(c, span)) in chars.iter().enumerate() {
+            let between_letters = i > 0
+                && chars[i - 1].0.is_alphabetic()
+                && chars.get(i + 1).is_some_and(|c| c.0.is_alphabetic());
+            let to = match c {
+                '„' | '“' | '”' | '«' | '»' => "\"",
+                '‘' | '‚' | '´' | '`' => "'",
+                '’' if between_letters => "'",
+                '’' => "\"",
+                '–' => "-",
+                '—' => " - ",
+                '…' => "...",
+                '\u{a0}' => " ",
+                '(' => " (",
+                ')' => ") ",
+                c => {
+                    res.push((c, span));
+                    continue;
+                }
+            };
+            push(&mut res, to, span);
+        }
+
+        // Collapses and trims the whitespace.
+        let mut collapsed = Spanned::with_capacity(res.len());
+        for (c, span) in res {
+            if !c.is_whitespace() {
+                collapsed.push((c, span));
+            } else if collapsed.last().is_some_and(|&(c, _)| c != ' ') {
+                collapsed.push((' ', span));
+            }
+        }
+        if collapsed.last().is_some_and(|&(c, _)| c == ' ') {
+            collapsed.pop();
+        }
+
+        let mut res = collapsed;
+        for (from, to) in [
+            ("''", "\""),
+            ("( ", "("),
+            (" )", ")"),
+            (" %", "%"),
+            (" :", ":"),
+            (" ;", ";"),
+            (" ?", "?"),
+            (" !", "!"),
+        ] {
+            res = replace(res, from, to);
+        }
+        for punct in [".", "!", ":", "?", ";", ","] {
+            res = replace(res, &format!(") {punct}"), &format!("){punct}"));
+        }
+        if self.lang == "en" {
+            // "quotation," followed by comma, style
+            res = replace(replace(res, "\",", ",\""), "\".", ".\"");
+        }
+        res
+    }
+
+    /// Tokenizes the given text.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        self.tokenize_spanned(text)
+            .into_iter()
+            .map(|token| token.into_iter().map(|(c, _)| c).collect())
+            .collect()
+    }
+
+    /// Tokenizes the given text, keeping the byte span of the input every token comes from.
+    /// Escaped and normalized tokens span the characters they replace, e.g. `&amp;` spans `&`.
+    pub fn tokenize_with_offsets(&self, text: &str) -> Vec<(String, (usize, usize))> {
+        self.tokenize_spanned(text)
+            .into_iter()
+            .map(|token| {
+                let span = span_of(&token);
+                (token.into_iter().map(|(c, _)| c).collect(), span)
+            })
+            .collect()
+    }
+
+    /// Tokenizes the given text, keeping the span of every character of the tokens.
+    pub(crate) fn tokenize_spanned(&self, text: &str) -> Vec<Spanned> {
+        let chars = if self.options.normalize_punctuation {
+            self.normalize_spanned(text)
+        } else {
+            spanned(text)
+        };
+        let chars = chars
+            .into_iter()
+            .map(|(c, span)| (if c.is_control() { ' ' } else { c }, span))
+            .collect::<Vec<_>>();
+        let padded = self.pad(&chars);
+        let words = padded
+            .split(|&(c, _)| c.is_whitespace())
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>();
+        let texts = words
+            .iter()
+            .map(|w| w.iter().map(|&(c, _)| c).collect::<String>())
+            .collect::<Vec<_>>();
+
+        let mut res = Vec::with_capacity(words.len());
+        for (i, (word, text)) in words.iter().zip(&texts).enumerate() {
+            match text.strip_suffix('.') {
+                Some(pre) if !pre.is_empty() && !pre.ends_with('.') => {
+                    let next = texts.get(i + 1);
+                    let keep = (pre.contains('.') && pre.chars().any(char::is_alphabetic))
+                        || self.prefixes.get(pre) == Some(&Prefix::Always)
+                        || next.is_some_and(|n| n.starts_with(char::is_lowercase))
+                        || (self.prefixes.get(pre) == Some(&Prefix::NumericOnly)
+                            && next.is_some_and(|n| n.starts_with(|c: char| c.is_ascii_digit())));
+                    if keep {
+                        res.push(word.to_vec());
+                    } else {
+                        let (pre, dot) = word.split_at(word.len() - 1);
+                        res.push(pre.to_vec());
+                        res.push(dot.to_vec());
+                    }
+                }
+                _ => res.push(word.to_vec()),
+            }
+        }
+
+        if self.options.escape {
+            res.iter().map(|t| escape(t)).collect()
+        } else {
+            res
+        }
+    }
+
+    /// Inserts spaces around the characters that form tokens of their own.
+    fn pad(&self, text: &[(char, (usize, usize))]) -> Spanned {
+        let chars = text.iter().map(|&(c, _)| c).collect::<Vec<_>>();
+        let is_alpha = |i: Option<usize>| {
+            i.and_then(|i| chars.get(i))
+                .is_some_and(|c| c.is_alphabetic())
+        };
+        let is_num =
+            |i: Option<usize>| i.and_then(|i| chars.get(i)).is_some_and(|c| c.is_numeric());
+        let is_alnum = |i: Option<usize>| is_alpha(i) || is_num(i);
+        let elision = matches!(self.lang.as_str(), "fr" | "it" | "ga" | "ca");
+
+        let mut res = Spanned::with_capacity(text.len() * 2);
+        let mut i = 0;
+        while i < chars.len() {
+            let (c, span) = text[i];
+            let (prev, next) = (i.checked_sub(1), Some(i + 1));
+            match c {
+                '.' if chars.get(i + 1) == Some(&'.') => {
+                    // Multiple dots are one token.
+                    let len = chars[i..].iter().take_while(|&&c| c == '.').count();
+                    res.push((' ', span));
+                    res.extend(&text[i..i + len]);
+                    res.push((' ', span));
+                    i += len;
+                    continue;
+                }
+                '.' | '`' => res.push((c, span)),
+                '-' if self.options.aggressive_dash_splits && is_alnum(prev) && is_alnum(next) => {
+                    push(&mut res, " @-@ ", span);
+                }
+                '-' => res.push((c, span)),
+                ',' if is_num(prev) && is_num(next) => res.push((c, span)),
+                '\'' if self.lang == "en" => match (is_alpha(prev), is_alpha(next)) {
+                    (true, true) => push(&mut res, " '", span),
+                    // e.g. 1990's
+                    (false, true) if is_num(prev) && chars.get(i + 1) == Some(&'s') => {
+                        push(&mut res, " '", span);
+                    }
+                    (false, true) if is_num(prev) => res.push((c, span)),
+                    _ => push(&mut res, " ' ", span),
+                },
+                '\'' if elision => match (is_alpha(prev), is_alpha(next)) {
+                    (true, true) => push(&mut res, "' ", span),
+                    _ => push(&mut res, " ' ", span),
+                },
+                c if c.is_alphanumeric() || c.is_whitespace() => res.push((c, span)),
+                c => {
+                    res.push((' ', span));
+                    res.push((c, span));
+                    res.push((' ', span));
+                }
+            }
+            i += 1;
+        }
+        res
+    }
+
+    /// Restores the text from tokens produced by [`tokenize`](Self::tokenize).
+    pub fn detokenize<S: AsRef<str>>(&self, tokens: &[S]) -> String {
+        let mut state = Detokenizer::default();
+        let res = tokens
+            .iter()
+            .map(|t| self.detokenize_word(&mut state, &unescape(t.as_ref())))
+            .collect::<String>();
+        res.trim().to_owned()
+    }
+
+    /// Returns the text of the next unescaped word, preceded by a space unless it is attached
+    /// to the previous word.
+    fn detokenize_word(&self, state: &mut Detokenizer, word: &str) -> String {
+        let elision = matches!(self.lang.as_str(), "fr" | "it" | "ga" | "ca");
+        if std::mem::take(&mut state.elided) && word.starts_with(char::is_alphabetic) {
+            state.space = "";
+        }
+        let prev = state.prev.replace(word.to_owned());
+        let space = std::mem::replace(&mut state.space, " ");
+
+        let all = |set: &str| !word.is_empty() && word.chars().all(|c| set.contains(c));
+        if word == DASH {
+            state.space = "";
+            "-".to_owned()
+        } else if all(CURRENCIES) || all("([{¿¡") {
+            state.space = "";
+            format!("{space}{word}")
+        } else if all(",.?!:;\\%}])") || (self.lang == "en" && prev.is_some() && is_clitic(word)) {
+            word.to_owned()
+        } else if elision
+            && word.ends_with('\'')
+            && word.chars().rev().nth(1).is_some_and(char::is_alphabetic)
+        {
+            // Attached to the next word if it starts with a letter.
+            state.elided = true;
+            format!("{space}{word}")
+        } else if all("'\"`") {
+            let count = state.quotes.entry(word.to_owned()).or_insert(0);
+            let possessive =
+                self.lang == "en" && word == "'" && prev.is_some_and(|p| p.ends_with('s'));
+            if !possessive {
+                *count += 1;
+            }
+            if *count % 2 == 1 && !possessive {
+                // Opening quote.
+                state.space = "";
+                format!("{space}{word}")
+            } else {
+                word.to_owned()
+            }
+        } else {
+            format!("{space}{word}")
+        }
+    }
+}
+
+/// The state of the detokenization of a sequence of words.
+#[derive(Clone, Debug, Default)]
+struct Detokenizer {
+    /// The number of occurrences of each quote.
+    quotes: HashMap<String, usize>,
+    /// The space before the next word.
+    space: &'static str,
+    prev: Option<String>,
+    /// Whether the previous word is an elision, e.g. `l'`.
+    elided: bool,
+}
+
+/// Streaming decoder for Moses tokens, optionally split into subwords ending with a separator.
+/// The words are detokenized once complete.
+pub(crate) struct Decoder<'a> {
+    tokenizer: &'a Tokenizer,
+    special_tokens: &'a SpecialTokens,
+    separator: Option<&'a str>,
+    state: Detokenizer,
+    /// The subwords of the current word.
+    word: String,
+    started: bool,
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(
+        tokenizer: &'a Tokenizer,
+        special_tokens: &'a SpecialTokens,
+        separator: Option<&'a str>,
+    ) -> Self {
+        Self {
+            tokenizer,
+            special_tokens,
+            separator,
+            state: Detokenizer::default(),
+            word: String::new(),
+            started: false,
+        }
+    }
+
+    fn emit(&mut self) -> String {
+        let word = unescape(&std::mem::take(&mut self.word));
+        let text = self.tokenizer.detokenize_word(&mut self.state, &word);
+        if self.started {
+            return text;
+        }
+        let text = text.trim_start().to_owned();
+        self.started = !text.is_empty();
+        text
+    }
+}
+
+impl StreamingDecoder for Decoder<'_> {
+    fn push(&mut self, token: &str) -> Result<String> {
+        if self.special_tokens.is_special(token) {
+            return Ok(String::new());
+        }
+        match self.separator.and_then(|s| token.strip_suffix(s)) {
+            Some(piece) => {
+                self.word.push_str(piece);
+                Ok(String::new())
+            }
+            None => {
+                self.word.push_str(token);
+                Ok(self.emit())
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Result<String> {
+        if self.word.is_empty() {
+            return Ok(String::new());
+        }
+        Ok(self.emit())
+    }
+}
+
+/// Returns whether the word is an English clitic such as `'s` or `'t`.
+fn is_clitic(word: &str) -> bool {
+    word.strip_prefix('\'')
+        .is_some_and(|w| w.starts_with(char::is_alphabetic))
+}
+
+/// Returns the built-in nonbreaking prefixes of a language, in the Moses format.
+pub(crate) fn builtin_prefixes(lang: &str) -> &'static str {
+    match lang {
+        "en" => NONBREAKING_PREFIX_EN,
+        "de" => NONBREAKING_PREFIX_DE,
+        "fr" => NONBREAKING_PREFIX_FR,
+        _ => "",
+    }
+}
+
+pub(crate) fn parse_prefixes(content: &str, prefixes: &mut HashMap<String, Prefix>) {
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once(NUMERIC_ONLY) {
+            Some((prefix, _)) => prefixes.insert(prefix.trim().to_owned(), Prefix::NumericOnly),
+            None => prefixes.insert(line.to_owned(), Prefix::Always),
+        };
+    }
+}
+
+/// The characters of a text, each with the byte span of the input it comes from.
+pub(crate) type Spanned = Vec<(char, (usize, usize))>;
+
+fn spanned(text: &str) -> Spanned {
+    text.char_indices()
+        .map(|(i, c)| (c, (i, i + c.len_utf8())))
+        .collect()
+}
+
+/// Returns the smallest span covering the spans of the characters.
+pub(crate) fn span_of(chars: &[(char, (usize, usize))]) -> (usize, usize) {
+    let start = chars.iter().map(|&(_, (s, _))| s).min().unwrap_or_default();
+    let end = chars.iter().map(|&(_, (_, e))| e).max().unwrap_or(start);
+    (start, end)
+}
+
+/// Appends the characters of `text`, all with the given span.
+fn push(res: &mut Spanned, text: &str, span: (usize, usize)) {
+    res.extend(text.chars().map(|c| (c, span)));
+}
+
+/// Replaces the occurrences of `from` with `to` like [`str::replace`]. A character of `to`
+/// found in the replaced characters keeps its span; the others span all of them.
+fn replace(chars: Spanned, from: &str, to: &str) -> Spanned {
+    let from = from.chars().collect::<Vec<_>>();
+    let mut res = Spanned::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let replaced = &chars[i..chars.len().min(i + from.len())];
+        if !replaced.iter().map(|&(c, _)| c).eq(from.iter().copied()) {
+            res.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let all = span_of(replaced);
+        let mut used = vec![false; replaced.len()];
+        for c in to.chars() {
+            let span = match (0..replaced.len()).find(|&k| !used[k] && replaced[k].0 == c) {
+                Some(k) => {
+                    used[k] = true;
+                    replaced[k].1
+                }
+                None => all,
+            };
+            res.push((c, span));
+        }
+        i += from.len();
+    }
+    res
+}
+
+fn escape(token: &[(char, (usize, usize))]) -> Spanned {
+    let mut res = Spanned::with_capacity(token.len());
+    for &(c, span) in token {
+        match ESCAPES.iter().find(|(from, _)| *from == c) {
+            Some((_, to)) => push(&mut res, to, span),
+            None => res.push((c, span)),
+        }
+    }
+    res
+}
+
+fn unescape(token: &str) -> String {
+    if !token.contains('&') {
+        return token.to_owned();
+    }
+    // `&amp;` is last, so that an escaped escape sequence is restored literally.
+    ESCAPES
+        .iter()
+        .rev()
+        .fold(token.to_owned(), |res, (c, escaped)| {
+            res.replace(escaped, &c.to_string())
+        })
+}
+
+impl crate::Tokenizer for Tokenizer {
+    fn encode(&self, input: &str) -> Result<Vec<String>> {
+        Ok(self.special_tokens.apply_source(self.tokenize(input)))
+    }
+
+    fn decode(&self, tokens: Vec<String>) -> Result<String> {
+        Ok(self.detokenize(&self.special_tokens.strip(tokens)))
+    }
+
+    fn encode_prefix(&self, input: &str) -> Result<Vec<String>> {
+        Ok(self
+            .special_tokens
+            .apply_target_prefix(self.tokenize(input)))
+    }
+
+    fn encode_with_offsets(&self, input: &str) -> Result<Vec<(String, (usize, usize))>> {
+        Ok(self
+            .special_tokens
+            .apply_source_with_offsets(self.tokenize_with_offsets(input), input.len()))
+    }
+
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        Box::new(Decoder::new(self, &self.special_tokens, None))
+    }
+
+    fn adds_language_tokens(&self) -> bool {
+        self.special_tokens.has_languages()
+    }
+}
+
+#[test]
+fn moses_round_trip() {
+    let en = Tokenizer::new("en");
+    for (text, expected) in [
+        (
+            "It costs $5,000 (approx.) in the U.S. today...",
+            "It costs $ 5,000 ( approx . ) in the U.S. today ...",
+        ),
+        (
+            "\"Hello,\" said Dr. Brown, \"it's No. 5 & Chris' car.\"",
+            "&quot; Hello , &quot; said Dr. Brown , &quot; it &apos;s No. 5 &amp; Chris &apos; car . &quot;",
+        ),
+    ] {
+        let tokens = en.tokenize(text);
+        assert_eq!(tokens.join(" "), expected);
+        assert_eq!(en.detokenize(&tokens), text);
+        let mut decoder = crate::Tokenizer::streaming_decoder(&en);
+        let streamed = tokens
+            .iter()
+            .map(|token| decoder.push(token).unwrap())
+            .collect::<String>();
+        assert_eq!(streamed + &decoder.finish().unwrap(), text);
+    }
+
+    let text = "“Tom & Jerry” (1940)";
+    let tokens = en.tokenize_with_offsets(text);
+    assert_eq!(
+        tokens.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>(),
+        [
+            "&quot;", "Tom", "&amp;", "Jerry", "&quot;", "(", "1940", ")"
+        ]
+    );
+    for (token, (start, end)) in tokens {
+        let token = unescape(&token);
+        assert_eq!(en.normalize(&text[start..end]), token);
+    }
+
+    let mut fr = Tokenizer::new("fr");
+    fr.with_options(Options {
+        aggressive_dash_splits: true,
+        ..Default::default()
+    });
+    let tokens = fr.tokenize("L'arc-en-ciel, c'est beau !");
+    assert_eq!(
+        tokens.join(" "),
+        "L&apos; arc @-@ en @-@ ciel , c&apos; est beau !"
+    );
+    assert_eq!(fr.detokenize(&tokens), "L'arc-en-ciel, c'est beau!");
+}
@@ -0,0 +1,750 @@
+//! A pure-Rust implementation of the [OpenNMT Tokenizer](https://github.com/OpenNMT/Tokenizer),
+//! also known as `pyonmttok`.
+//!
+//! The text is first split into words and punctuation according to the [`Mode`]. Tokens that
+//! were attached in the text are marked with a joiner (`￭`) or, alternatively, the tokens
+//! preceded by a space with a spacer (`▁`), so that [`Tokenizer::detokenize`] can restore the
+//! original text. With `case_markup`, words are lower cased and their casing is encoded with
+//! case markup tokens. Words can finally be split into subwords with BPE or SentencePiece, see
+//! [`Subword`].
+//!
+//! ```
+//! use ctranslate2::tokenizer::onmt::{Mode, Options, Tokenizer};
+//!
+//! let t = Tokenizer::new(Options {
+//!     mode: Mode::Aggressive,
+//!     joiner_annotate: true,
+//!     ..Default::default()
+//! });
+//! let tokens = t.tokenize("Isn't it so-greatly working?");
+//! assert_eq!(tokens.join(" "), "Isn ￭'￭ t it so ￭-￭ greatly working ￭?");
+//! assert_eq!(t.detokenize(&tokens), "Isn't it so-greatly working?");
+//! ```
+//!
+//! Placeholders such as `｟URL｠` are kept as single tokens. Words mixing lower and upper case
+//! letters, e.g. `iPhone`, are not lower cased by `case_markup`.
+
+use anyhow::Result;
+
+use crate::tokenizer::bpe_codes::BpeCodes;
+#[cfg(feature = "rust_tokenizers")]
+use crate::tokenizer::rust_tokenizers::SentenceTokenizer;
+use crate::tokenizer::{SpecialTokens, StreamingDecoder};
+
+/// The default joiner.
+pub const JOINER: &str = "￭";
+/// The spacer.
+pub const SPACER: &str = "▁";
+/// Marks the next token as capitalized.
+pub const CASE_MODIFIER_C: &str = "｟mrk_case_modifier_C｠";
+/// Starts a region of upper case tokens.
+pub const BEGIN_CASE_REGION_U: &str = "｟mrk_begin_case_region_U｠";
+/// Ends a region of upper case tokens.
+pub const END_CASE_REGION_U: &str = "｟mrk_end_case_region_U｠";
+
+const PLACEHOLDER_BEGIN: char = '｟';
+const PLACEHOLDER_END: char = '｠';
+
+/// How the text is split before applying subwords.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Splits on spaces and punctuation, but keeps numbers such as `2,000` and words such as
+    /// `so-greatly` or `A380` together.
+    #[default]
+    Conservative,
+    /// Splits on spaces and punctuation, and between letters and numbers.
+    Aggressive,
+    /// Splits on spaces only.
+    Space,
+    /// Splits every character.
+    Char,
+    /// Does not split; usually combined with a SentencePiece model.
+    None,
+}
+
+/// Options of the OpenNMT tokenizer, named after the `pyonmttok` options.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub mode: Mode,
+    /// Mark tokens attached to the previous or next token with `joiner`.
+    pub joiner_annotate: bool,
+    /// Insert the joiner as a token of its own. Requires `joiner_annotate`.
+    pub joiner_new: bool,
+    /// The joiner, `￭` by default.
+    pub joiner: String,
+    /// Mark tokens preceded by a space with `▁`, instead of using joiners.
+    pub spacer_annotate: bool,
+    /// Insert the spacer as a token of its own. Requires `spacer_annotate`.
+    pub spacer_new: bool,
+    /// Lower case words and encode their casing with case markup tokens.
+    pub case_markup: bool,
+    /// Split numbers into single digits, in aggressive mode.
+    pub segment_numbers: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            mode: Mode::default(),
+            joiner_annotate: false,
+            joiner_new: false,
+            joiner: JOINER.to_owned(),
+            spacer_annotate: false,
+            spacer_new: false,
+            case_markup: false,
+            segment_numbers: false,
+        }
+    }
+}
+
+/// A subword model applied to every word.
+pub enum Subword {
+    /// BPE merge operations.
+    Bpe(BpeCodes),
+    /// A SentencePiece model.
+    #[cfg(feature = "rust_tokenizers")]
+    SentencePiece(Box<SentenceTokenizer>),
+}
+
+impl Subword {
+    fn apply(&self, word: &str) -> Vec<String> {
+        match self {
+            Subword::Bpe(codes) => codes.apply(word),
+            #[cfg(feature = "rust_tokenizers")]
+            Subword::SentencePiece(spp) => spp
+                .tokenize(word)
+                .into_iter()
+                .map(|piece| piece.trim_start_matches(SPACER).to_owned())
+                .filter(|piece| !piece.is_empty())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    /// Letters and numbers.
+    Word,
+    /// A punctuation mark or a symbol.
+    Other,
+    /// A placeholder such as `｟URL｠`.
+    Placeholder,
+    /// A case markup token.
+    Marker,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Case {
+    None,
+    Lower,
+    Capitalized,
+    Upper,
+    Mixed,
+}
+
+impl Case {
+    fn of(text: &str) -> Self {
+        let cased = text
+            .chars()
+            .filter(|c| c.is_lowercase() || c.is_uppercase())
+            .collect::<Vec<_>>();
+        let upper = cased.iter().filter(|c| c.is_uppercase()).count();
+        match cased.first() {
+            None => Case::None,
+            Some(_) if upper == 0 => Case::Lower,
+            Some(first) if first.is_uppercase() && upper == 1 => Case::Capitalized,
+            Some(_) if upper == cased.len() => Case::Upper,
+            Some(_) => Case::Mixed,
+        }
+    }
+}
+
+/// A token and its position in the input.
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    kind: Kind,
+    /// Not separated from the previous token by a space.
+    joined: bool,
+    /// Annotated with a joiner on the left.
+    join_left: bool,
+    /// Annotated with a joiner on the right.
+    join_right: bool,
+    /// The byte span in the input.
+    span: (usize, usize),
+}
+
+impl Token {
+    fn new(text: String, kind: Kind, joined: bool, span: (usize, usize)) -> Self {
+        Self {
+            text,
+            kind,
+            joined,
+            join_left: false,
+            join_right: false,
+            span,
+        }
+    }
+
+    fn marker(text: &str, at: usize) -> Self {
+        Self::new(text.to_owned(), Kind::Marker, false, (at, at))
+    }
+}
+
+/// The OpenNMT tokenizer.
+pub struct Tokenizer {
+    options: Options,
+    subword: Option<Subword>,
+    special_tokens: SpecialTokens,
+}
+
+impl Tokenizer {
+    /// Create a tokenizer with the given options and no subword model.
+    pub fn new(options: Options) -> Self {
+        Self {
+            options,
+            subword: None,
+            special_tokens: SpecialTokens::default(),
+        }
+    }
+
+    /// Sets the subword model applied to every word.
+    pub fn with_subword(&mut self, subword: Subword) -> &mut Self {
+        self.subword = Some(subword);
+        self
+    }
+
+    /// Sets the special tokens added when encoding and removed when decoding.
+    pub fn with_special_tokens(&mut self, special_tokens: SpecialTokens) -> &mut Self {
+        self.special_tokens = special_tokens;
+        self
+    }
+
+    /// Returns the options.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// Tokenizes the given text.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        self.tokenize_with_offsets(text)
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect()
+    }
+
+    /// Tokenizes the given text, returning the byte span of each token. Case markup tokens,
+    /// and joiners and spacers inserted as tokens, have an empty span.
+    pub fn tokenize_with_offsets(&self, text: &str) -> Vec<(String, (usize, usize))> {
+        let mut tokens = self.split(text);
+        annotate_joiners(&mut tokens);
+        if self.options.case_markup {
+            tokens = case_markup(tokens);
+        }
+        if let Some(subword) = &self.subword {
+            tokens = apply_subword(subword, text, tokens);
+        }
+        self.render(tokens)
+    }
+
+    /// Restores the text from tokens produced by [`tokenize`](Self::tokenize).
+    pub fn detokenize<S: AsRef<str>>(&self, tokens: &[S]) -> String {
+        let mut state = Detokenizer::default();
+        tokens
+            .iter()
+            .map(|t| self.detokenize_token(&mut state, t.as_ref()))
+            .collect()
+    }
+
+    /// Returns the text of the next token, preceded by a space unless it is attached to the
+    /// previous token.
+    fn detokenize_token(&self, state: &mut Detokenizer, token: &str) -> String {
+        let joiner = self.options.joiner.as_str();
+        let mut text = token;
+        match text {
+            CASE_MODIFIER_C => state.capitalize = true,
+            BEGIN_CASE_REGION_U => state.upper = true,
+            END_CASE_REGION_U => state.upper = false,
+            _ => {}
+        }
+        if matches!(
+            text,
+            CASE_MODIFIER_C | BEGIN_CASE_REGION_U | END_CASE_REGION_U
+        ) {
+            return String::new();
+        }
+
+        let mut attach = std::mem::take(&mut state.attach_next);
+        if self.options.spacer_annotate {
+            if text == SPACER {
+                state.space_next = true;
+                return String::new();
+            }
+            let spaced = match text.strip_prefix(SPACER) {
+                Some(t) => {
+                    text = t;
+                    true
+                }
+                None => std::mem::take(&mut state.space_next),
+            };
+            attach = !spaced;
+        } else if self.options.joiner_annotate {
+            if text == joiner {
+                state.attach_next = true;
+                return String::new();
+            }
+            if let Some(t) = text.strip_prefix(joiner) {
+                text = t;
+                attach = true;
+            }
+            if let Some(t) = text.strip_suffix(joiner) {
+                text = t;
+                state.attach_next = true;
+            }
+        }
+
+        let mut res = String::new();
+        if !attach && state.started {
+            res.push(' ');
+        }
+        if state.upper {
+            res.push_str(&text.to_uppercase());
+        } else if std::mem::take(&mut state.capitalize) {
+            let mut chars = text.chars();
+            res.extend(chars.next().into_iter().flat_map(char::to_uppercase));
+            res.push_str(chars.as_str());
+        } else {
+            res.push_str(text);
+        }
+        state.started |= !res.is_empty();
+        res
+    }
+
+    /// Splits the text according to the mode.
+    fn split(&self, text: &str) -> Vec<Token> {
+        let words = || {
+            text.split_whitespace().map(|word| {
+                // `word` is a subslice of `text`.
+                let start = word.as_ptr() as usize - text.as_ptr() as usize;
+                (word, start)
+            })
+        };
+        match self.options.mode {
+            Mode::None if text.is_empty() => vec![],
+            Mode::None => vec![Token::new(
+                text.to_owned(),
+                Kind::Word,
+                false,
+                (0, text.len()),
+            )],
+            Mode::Space => words()
+                .map(|(word, start)| {
+                    Token::new(
+                        word.to_owned(),
+                        Kind::Word,
+                        false,
+                        (start, start + word.len()),
+                    )
+                })
+                .collect(),
+            Mode::Char => words()
+                .flat_map(|(word, start)| {
+                    word.char_indices().map(move |(i, c)| {
+                        let kind = if c.is_alphanumeric() {
+                            Kind::Word
+                        } else {
+                            Kind::Other
+                        };
+                        let begin = start + i;
+                        Token::new(c.to_string(), kind, i > 0, (begin, begin + c.len_utf8()))
+                    })
+                })
+                .collect(),
+            Mode::Conservative | Mode::Aggressive => self.split_words(text),
+        }
+    }
+
+    /// Splits the text on spaces and punctuation, for the conservative and aggressive modes.
+    fn split_words(&self, text: &str) -> Vec<Token> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum CharKind {
+            Letter,
+            Number,
+        }
+
+        let conservative = self.options.mode == Mode::Conservative;
+        let chars = text.char_indices().collect::<Vec<_>>();
+        let mut tokens = Vec::new();
+        let mut current = String::new();
+        let mut current_start = 0;
+        let mut current_joined = false;
+        let mut last = None;
+        let mut space = true;
+
+        let flush = |tokens: &mut Vec<Token>, current: &mut String, start: usize, joined: bool| {
+            if !current.is_empty() {
+                let end = start + current.len();
+                tokens.push(Token::new(
+                    std::mem::take(current),
+                    Kind::Word,
+                    joined,
+                    (start, end),
+                ));
+            }
+        };
+
+        let mut i = 0;
+        while i < chars.len() {
+            let (pos, c) = chars[i];
+            if c.is_whitespace() {
+                flush(&mut tokens, &mut current, current_start, current_joined);
+                last = None;
+                space = true;
+                i += 1;
+                continue;
+            }
+
+            if c == PLACEHOLDER_BEGIN
+                && let Some(len) = chars[i..].iter().position(|&(_, c)| c == PLACEHOLDER_END)
+            {
+                flush(&mut tokens, &mut current, current_start, current_joined);
+                let end = chars[i + len].0 + PLACEHOLDER_END.len_utf8();
+                tokens.push(Token::new(
+                    text[pos..end].to_owned(),
+                    Kind::Placeholder,
+                    !space,
+                    (pos, end),
+                ));
+                last = None;
+                space = false;
+                i += len + 1;
+                continue;
+            }
+
+            let kind = if c.is_alphabetic() {
+                Some(CharKind::Letter)
+            } else if c.is_numeric() {
+                Some(CharKind::Number)
+            } else {
+                None
+            };
+            match kind {
+                None => {
+                    let next = chars.get(i + 1).map(|&(_, c)| c);
+                    let keep = conservative
+                        && last.is_some()
+                        && next.is_some_and(|n| match c {
+                            '-' | '_' => n.is_alphanumeric(),
+                            '.' | ',' => last == Some(CharKind::Number) && n.is_numeric(),
+                            _ => false,
+                        });
+                    if keep {
+                        current.push(c);
+                    } else {
+                        flush(&mut tokens, &mut current, current_start, current_joined);
+                        tokens.push(Token::new(
+                            c.to_string(),
+                            Kind::Other,
+                            !space,
+                            (pos, pos + c.len_utf8()),
+                        ));
+                        last = None;
+                        space = false;
+                    }
+                }
+                Some(kind) => {
+                    let same = match (last, kind) {
+                        (Some(CharKind::Letter), CharKind::Letter) => true,
+                        (Some(CharKind::Number), CharKind::Number) => {
+                            conservative || !self.options.segment_numbers
+                        }
+                        _ => conservative,
+                    };
+                    if !same {
+                        flush(&mut tokens, &mut current, current_start, current_joined);
+                    }
+                    if current.is_empty() {
+                        current_start = pos;
+                        current_joined = !space;
+                    }
+                    current.push(c);
+                    last = Some(kind);
+                    space = false;
+                }
+            }
+            i += 1;
+        }
+        flush(&mut tokens, &mut current, current_start, current_joined);
+        tokens
+    }
+
+    /// Turns the tokens into strings with the configured joiners or spacers.
+    fn render(&self, tokens: Vec<Token>) -> Vec<(String, (usize, usize))> {
+        let options = &self.options;
+        let mut res = Vec::with_capacity(tokens.len());
+        let mut started = false;
+
+        for token in tokens {
+            let (start, end) = token.span;
+            if token.kind == Kind::Marker {
+                res.push((token.text, token.span));
+                continue;
+            }
+
+            if options.spacer_annotate {
+                let spaced = started && !token.joined;
+                if spaced && options.spacer_new {
+                    res.push((SPACER.to_owned(), (start, start)));
+                    res.push((token.text, token.span));
+                } else if spaced {
+                    res.push((format!("{SPACER}{}", token.text), token.span));
+                } else {
+                    res.push((token.text, token.span));
+                }
+            } else if options.joiner_annotate && options.joiner_new {
+                if token.join_left {
+                    res.push((options.joiner.clone(), (start, start)));
+                }
+                res.push((token.text, token.span));
+                if token.join_right {
+                    res.push((options.joiner.clone(), (end, end)));
+                }
+            } else if options.joiner_annotate {
+                let mut text = token.text;
+                if token.join_left {
+                    text.insert_str(0, &options.joiner);
+                }
+                if token.join_right {
+                    text.push_str(&options.joiner);
+                }
+                res.push((text, token.span));
+            } else {
+                res.push((token.text, token.span));
+            }
+            started = true;
+        }
+        res
+    }
+}
+
+/// Marks where joiners go: on the punctuation side when a word and a punctuation mark are
+/// attached, and on the left of the second token otherwise.
+fn annotate_joiners(tokens: &mut [Token]) {
+    for i in 1..tokens.len() {
+        if !tokens[i].joined {
+            continue;
+        }
+        if tokens[i].kind != Kind::Word {
+            tokens[i].join_left = true;
+        } else if tokens[i - 1].kind != Kind::Word {
+            tokens[i - 1].join_right = true;
+        } else {
+            tokens[i].join_left = true;
+        }
+    }
+}
+
+/// The state of the detokenization of a sequence of tokens.
+#[derive(Clone, Debug, Default)]
+struct Detokenizer {
+    attach_next: bool,
+    space_next: bool,
+    capitalize: bool,
+    upper: bool,
+    /// Whether some text was returned.
+    started: bool,
+}
+
+/// Streaming decoder placing the spaces according to the joiners or spacers of the tokens.
+struct Decoder<'a> {
+    tokenizer: &'a Tokenizer,
+    state: Detokenizer,
+}
+
+impl StreamingDecoder for Decoder<'_> {
+    fn push(&mut self, token: &str) -> Result<String> {
+        if self.tokenizer.special_tokens.is_special(token) {
+            return Ok(String::new());
+        }
+        Ok(self.tokenizer.detokenize_token(&mut self.state, token))
+    }
+
+    fn finish(&mut self) -> Result<String> {
+        Ok(String::new())
+    }
+}
+
+/// Lower cases the words and inserts the case markup tokens.
+///
+/// A capitalized word is preceded by [`CASE_MODIFIER_C`]. Upper case words are enclosed in
+/// [`BEGIN_CASE_REGION_U`] and [`END_CASE_REGION_U`], together with the following upper case
+/// words and the caseless tokens between them.
+fn case_markup(tokens: Vec<Token>) -> Vec<Token> {
+    let cases = tokens
+        .iter()
+        .map(|t| match t.kind {
+            Kind::Word => Case::of(&t.text),
+            _ => Case::None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut res = Vec::with_capacity(tokens.len());
+    let mut region_end = None;
+    for (i, mut token) in tokens.into_iter().enumerate() {
+        let start = token.span.0;
+        match cases[i] {
+            Case::Upper if region_end.is_none() => {
+                let end = (i + 1..cases.len())
+                    .take_while(|&j| matches!(cases[j], Case::Upper | Case::None))
+                    .filter(|&j| cases[j] == Case::Upper)
+                    .last()
+                    .unwrap_or(i);
+                region_end = Some(end);
+                res.push(Token::marker(BEGIN_CASE_REGION_U, start));
+                token.text = token.text.to_lowercase();
+            }
+            Case::Upper => token.text = token.text.to_lowercase(),
+            Case::Capitalized if region_end.is_none() => {
+                res.push(Token::marker(CASE_MODIFIER_C, start));
+                token.text = token.text.to_lowercase();
+            }
+            _ => {}
+        }
+
+        let end = token.span.1;
+        res.push(token);
+        if region_end == Some(i) {
+            res.push(Token::marker(END_CASE_REGION_U, end));
+            region_end = None;
+        }
+    }
+    res
+}
+
+/// Splits the words into subwords. The subwords of a word are attached to each other.
+fn apply_subword(subword: &Subword, text: &str, tokens: Vec<Token>) -> Vec<Token> {
+    let mut res = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        if token.kind != Kind::Word {
+            res.push(token);
+            continue;
+        }
+        let pieces = subword.apply(&token.text);
+        if pieces.len() <= 1 {
+            res.push(token);
+            continue;
+        }
+
+        // Subword spans are computed on the original characters of the word.
+        let (start, end) = token.span;
+        let bounds = text[start..end]
+            .char_indices()
+            .map(|(i, _)| start + i)
+            .chain([end])
+            .collect::<Vec<_>>();
+        let bound = |n: usize| bounds[n.min(bounds.len() - 1)];
+
+        let last = pieces.len() - 1;
+        let mut pos = 0;
+        for (i, piece) in pieces.into_iter().enumerate() {
+            let len = piece.chars().count();
+            let mut t = Token::new(piece, Kind::Word, true, (bound(pos), bound(pos + len)));
+            pos += len;
+            t.join_left = i > 0 || token.join_left;
+            t.join_right = i == last && token.join_right;
+            if i == 0 {
+                t.joined = token.joined;
+            }
+            res.push(t);
+        }
+    }
+    res
+}
+
+impl crate::Tokenizer for Tokenizer {
+    fn encode(&self, input: &str) -> Result<Vec<String>> {
+        Ok(self.special_tokens.apply_source(self.tokenize(input)))
+    }
+
+    fn decode(&self, tokens: Vec<String>) -> Result<String> {
+        Ok(self.detokenize(&self.special_tokens.strip(tokens)))
+    }
+
+    fn encode_prefix(&self, input: &str) -> Result<Vec<String>> {
+        Ok(self
+            .special_tokens
+            .apply_target_prefix(self.tokenize(input)))
+    }
+
+    fn encode_with_offsets(&self, input: &str) -> Result<Vec<(String, (usize, usize))>> {
+        Ok(self
+            .special_tokens
+            .apply_source_with_offsets(self.tokenize_with_offsets(input), input.len()))
+    }
+
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        Box::new(Decoder {
+            tokenizer: self,
+            state: Detokenizer::default(),
+        })
+    }
+
+    fn adds_language_tokens(&self) -> bool {
+        self.special_tokens.has_languages()
+    }
+}
+
+#[test]
+fn reference_fixtures() {
+    let codes = BpeCodes::parse(include_str!("../../tests/fixtures/onmt/bpe.codes")).unwrap();
+    let fixtures = include_str!("../../tests/fixtures/onmt/tokenize.txt");
+
+    let cases = fixtures
+        .split("\n\n")
+        .map(|case| {
+            case.lines()
+                .filter(|l| !l.starts_with('#'))
+                .collect::<Vec<_>>()
+        })
+        .filter(|lines| !lines.is_empty());
+    for lines in cases {
+        let [options, input, expected] = lines[..] else {
+            panic!("invalid fixture: {lines:?}");
+        };
+        let mut o = Options::default();
+        let mut bpe = false;
+        for option in options.strip_prefix("options:").unwrap().split_whitespace() {
+            match option {
+                "mode=conservative" => o.mode = Mode::Conservative,
+                "mode=aggressive" => o.mode = Mode::Aggressive,
+                "mode=space" => o.mode = Mode::Space,
+                "mode=char" => o.mode = Mode::Char,
+                "joiner_annotate" => o.joiner_annotate = true,
+                "joiner_new" => o.joiner_new = true,
+                "spacer_annotate" => o.spacer_annotate = true,
+                "spacer_new" => o.spacer_new = true,
+                "case_markup" => o.case_markup = true,
+                "segment_numbers" => o.segment_numbers = true,
+                "bpe" => bpe = true,
+                _ => panic!("unknown option: {option}"),
+            }
+        }
+        let reversible = o.joiner_annotate || o.spacer_annotate;
+        let mut t = Tokenizer::new(o);
+        if bpe {
+            t.with_subword(Subword::Bpe(codes.clone()));
+        }
+
+        let tokens = t.tokenize(input);
+        assert_eq!(tokens.join(" "), expected, "{options}: {input}");
+        if reversible {
+            assert_eq!(t.detokenize(&tokens), input, "{options}: {input}");
+            let mut decoder = crate::Tokenizer::streaming_decoder(&t);
+            let streamed = tokens
+                .iter()
+                .map(|token| decoder.push(token).unwrap())
+                .collect::<String>();
+            assert_eq!(streamed, input, "{options}: {input}");
+        }
+    }
+}
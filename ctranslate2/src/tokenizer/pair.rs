@@ -0,0 +1,111 @@
+//! Combines a source and a target tokenizer into a single [`Tokenizer`](crate::Tokenizer).
+//!
+//! Many models, e.g. Marian/OPUS models, ship distinct `source.spm` and `target.spm` files.
+//! [`TokenizerPair`] encodes sources with the first tokenizer, and encodes target prefixes and
+//! decodes translations with the second one.
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use ctranslate2::Translator2;
+//! use ctranslate2::tokenizer::rust_tokenizers::SentenceTokenizer;
+//!
+//! # fn main() -> Result<()> {
+//! let path = "/path/to/model";
+//! let t = Translator2::with_tokenizers(
+//!     path,
+//!     &Default::default(),
+//!     SentenceTokenizer::new(format!("{path}/source.spm"))?,
+//!     SentenceTokenizer::new(format!("{path}/target.spm"))?,
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::Result;
+
+use crate::Tokenizer;
+use crate::tokenizer::StreamingDecoder;
+
+/// A tokenizer using `S` for the source side and `T` for the target side.
+#[derive(Clone, Debug)]
+pub struct TokenizerPair<S, T> {
+    /// Tokenizer encoding the source sentences.
+    pub source: S,
+    /// Tokenizer encoding target prefixes and decoding translations.
+    pub target: T,
+}
+
+impl<S: Tokenizer, T: Tokenizer> TokenizerPair<S, T> {
+    /// Create a pair from a source and a target tokenizer.
+    pub fn new(source: S, target: T) -> Self {
+        Self { source, target }
+    }
+}
+
+impl<S: Tokenizer, T: Tokenizer> Tokenizer for TokenizerPair<S, T> {
+    fn encode(&self, input: &str) -> Result<Vec<String>> {
+        self.source.encode(input)
+    }
+
+    fn decode(&self, tokens: Vec<String>) -> Result<String> {
+        self.target.decode(tokens)
+    }
+
+    fn encode_prefix(&self, input: &str) -> Result<Vec<String>> {
+        self.target.encode_prefix(input)
+    }
+
+    fn encode_batch(&self, inputs: &[&str]) -> Result<Vec<Vec<String>>> {
+        self.source.encode_batch(inputs)
+    }
+
+    fn decode_batch(&self, tokens: Vec<Vec<String>>) -> Result<Vec<String>> {
+        self.target.decode_batch(tokens)
+    }
+
+    fn encode_ids(&self, input: &str) -> Result<Vec<u32>> {
+        self.source.encode_ids(input)
+    }
+
+    fn decode_ids(&self, ids: &[u32]) -> Result<String> {
+        self.target.decode_ids(ids)
+    }
+
+    fn encode_with_offsets(&self, input: &str) -> Result<Vec<(String, (usize, usize))>> {
+        self.source.encode_with_offsets(input)
+    }
+
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        self.target.streaming_decoder()
+    }
+
+    fn adds_language_tokens(&self) -> bool {
+        self.source.adds_language_tokens() || self.target.adds_language_tokens()
+    }
+}
+
+#[test]
+fn encode_source_decode_target() {
+    use crate::Translator2;
+    use crate::backend::mock::MockBackend;
+    use crate::tokenizer::{SpecialTokens, whitespace};
+
+    let mut source = whitespace::Tokenizer::new();
+    source.with_special_tokens(SpecialTokens {
+        eos_token: "<eos>".to_owned(),
+        ..Default::default()
+    });
+    let mut target = whitespace::Tokenizer::new();
+    target.with_special_tokens(SpecialTokens {
+        target_lang: Some("<de>".to_owned()),
+        ..Default::default()
+    });
+    let t = Translator2::with_backend(MockBackend::echo(), TokenizerPair::new(source, target));
+    let res = t
+        .translate_batch(&["a b".to_owned()], Default::default())
+        .unwrap();
+    assert_eq!(t.backend().batches()[0], vec![vec!["a", "b", "<eos>"]]);
+    // The target tokenizer does not know `<eos>`, so it keeps it when decoding.
+    assert_eq!(res[0].text, "a b <eos>");
+    assert_eq!(t.tokenizer().encode_prefix("c").unwrap(), ["<de>", "c"]);
+}
@@ -1,26 +1,295 @@
+use std::fs;
 use std::path::Path;
+use std::str::FromStr;
 
-use rust_tokenizers::tokenizer::{SentencePieceTokenizer, Tokenizer as _};
+use rust_tokenizers::error::TokenizerError as RustTokenizerError;
+use rust_tokenizers::tokenizer::{
+    MultiThreadedTokenizer, SentencePieceBpeTokenizer, SentencePieceTokenizer,
+    Tokenizer as RustTokenizer,
+};
+use rust_tokenizers::vocab::{SentencePieceVocab, Vocab};
+
+use crate::tokenizer::streaming::SentencePieceDecoder;
+use crate::tokenizer::{SpecialTokens, StreamingDecoder, TokenizerError};
+
+/// The SentencePiece model types supported by [`SentenceTokenizer`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ModelType {
+    /// A unigram language model, the SentencePiece default.
+    #[default]
+    Unigram,
+    /// A byte pair encoding model, trained with `--model_type=bpe`.
+    Bpe,
+}
+
+impl FromStr for ModelType {
+    type Err = TokenizerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "unigram" => Ok(ModelType::Unigram),
+            "bpe" => Ok(ModelType::Bpe),
+            _ => Err(TokenizerError::UnsupportedModelType(s.to_owned())),
+        }
+    }
+}
+
+impl ModelType {
+    /// Reads the model type from the trainer spec of a SentencePiece `.model` file.
+    pub fn from_model_file<P: AsRef<Path>>(path: P) -> Result<Self, TokenizerError> {
+        let path = path.as_ref();
+        TokenizerError::check_file(path)?;
+        let model = fs::read(path).map_err(|err| TokenizerError::parse(path, err))?;
+        match trainer_model_type(&model) {
+            Some(1) => Ok(ModelType::Unigram),
+            Some(2) => Ok(ModelType::Bpe),
+            Some(3) => Err(TokenizerError::UnsupportedModelType("word".to_owned())),
+            Some(4) => Err(TokenizerError::UnsupportedModelType("char".to_owned())),
+            Some(n) => Err(TokenizerError::UnsupportedModelType(n.to_string())),
+            None => Err(TokenizerError::parse(path, "invalid SentencePiece model")),
+        }
+    }
+}
+
+/// A field value of a protobuf message.
+enum ProtoValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+fn read_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut res = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first()?;
+        *buf = rest;
+        res |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(res);
+        }
+    }
+    None
+}
+
+/// Returns the field numbers and values of a protobuf message, or none if it is malformed.
+fn proto_fields(mut buf: &[u8]) -> Option<Vec<(u64, ProtoValue<'_>)>> {
+    let mut res = Vec::new();
+    while !buf.is_empty() {
+        let key = read_varint(&mut buf)?;
+        let value = match key & 7 {
+            0 => ProtoValue::Varint(read_varint(&mut buf)?),
+            1 | 5 => {
+                buf = buf.get(if key & 7 == 1 { 8 } else { 4 }..)?;
+                ProtoValue::Fixed
+            }
+            2 => {
+                let len = usize::try_from(read_varint(&mut buf)?).ok()?;
+                let (bytes, rest) = buf.split_at_checked(len)?;
+                buf = rest;
+                ProtoValue::Bytes(bytes)
+            }
+            _ => return None,
+        };
+        res.push((key >> 3, value));
+    }
+    Some(res)
+}
+
+/// Returns the `trainer_spec.model_type` of a SentencePiece model protobuf, 1 (unigram) if
+/// unset, or none if the protobuf is malformed.
+fn trainer_model_type(model: &[u8]) -> Option<u64> {
+    let mut res = 1;
+    for (number, value) in proto_fields(model)? {
+        if let (2, ProtoValue::Bytes(spec)) = (number, value) {
+            for (number, value) in proto_fields(spec)? {
+                if let (3, ProtoValue::Varint(model_type)) = (number, value) {
+                    res = model_type;
+                }
+            }
+        }
+    }
+    Some(res)
+}
+
+/// Options used to load a [`SentenceTokenizer`].
+#[derive(Clone, Debug, Default)]
+pub struct SentenceTokenizerConfig {
+    pub model_type: ModelType,
+    /// Lower case the input before tokenizing it.
+    pub lower_case: bool,
+}
+
+enum Inner {
+    Unigram(SentencePieceTokenizer),
+    Bpe(SentencePieceBpeTokenizer),
+}
 
 pub struct SentenceTokenizer {
-    spp: SentencePieceTokenizer,
+    spp: Inner,
+    special_tokens: SpecialTokens,
 }
 
 impl SentenceTokenizer {
-    pub fn new<P: AsRef<Path>>(path: P) -> Self {
-        let spp = SentencePieceTokenizer::from_file(path, false).unwrap();
-        Self { spp }
+    /// Create a tokenizer from a SentencePiece unigram model.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, TokenizerError> {
+        Self::with_config(path, &SentenceTokenizerConfig::default())
+    }
+
+    /// Create a tokenizer from a SentencePiece model using the given options.
+    pub fn with_config<P: AsRef<Path>>(
+        path: P,
+        config: &SentenceTokenizerConfig,
+    ) -> Result<Self, TokenizerError> {
+        let path = path.as_ref();
+        TokenizerError::check_file(path)?;
+        let map_err = |err: RustTokenizerError| match err {
+            RustTokenizerError::FileNotFound(_) => TokenizerError::FileNotFound(path.to_path_buf()),
+            err => TokenizerError::parse(path, err),
+        };
+
+        let spp = match config.model_type {
+            ModelType::Unigram => Inner::Unigram(
+                SentencePieceTokenizer::from_file(path, config.lower_case).map_err(map_err)?,
+            ),
+            ModelType::Bpe => Inner::Bpe(
+                SentencePieceBpeTokenizer::from_file(path, config.lower_case).map_err(map_err)?,
+            ),
+        };
+        Ok(Self {
+            spp,
+            special_tokens: SpecialTokens::default(),
+        })
+    }
+
+    /// Sets the special tokens added when encoding and removed when decoding.
+    pub fn with_special_tokens(&mut self, special_tokens: SpecialTokens) -> &mut Self {
+        self.special_tokens = special_tokens;
+        self
+    }
+
+    pub(crate) fn tokenize(&self, input: &str) -> Vec<String> {
+        match &self.spp {
+            Inner::Unigram(spp) => spp.tokenize(input),
+            Inner::Bpe(spp) => spp.tokenize(input),
+        }
+    }
+
+    /// Tokenizes the input, converting the character offsets of rust_tokenizers to byte
+    /// offsets. Tokens without offset get an empty span at the end of the previous token.
+    fn tokenize_with_offsets(&self, input: &str) -> Vec<(String, (usize, usize))> {
+        let res = match &self.spp {
+            Inner::Unigram(spp) => spp.tokenize_with_offsets(input),
+            Inner::Bpe(spp) => spp.tokenize_with_offsets(input),
+        };
+        let bytes = input
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([input.len()])
+            .collect::<Vec<_>>();
+        let byte = |c: u32| bytes.get(c as usize).copied().unwrap_or(input.len());
+
+        let mut last = 0;
+        res.tokens
+            .into_iter()
+            .zip(res.offsets)
+            .map(|(token, offset)| {
+                let span = match offset {
+                    Some(offset) => (byte(offset.begin), byte(offset.end)),
+                    None => (last, last),
+                };
+                last = span.1;
+                (token, span)
+            })
+            .collect()
+    }
+
+    fn vocab(&self) -> &SentencePieceVocab {
+        match &self.spp {
+            Inner::Unigram(spp) => RustTokenizer::vocab(spp),
+            Inner::Bpe(spp) => RustTokenizer::vocab(spp),
+        }
     }
 }
 
 impl crate::Tokenizer for SentenceTokenizer {
     fn encode(&self, input: &str) -> anyhow::Result<Vec<String>> {
-        let mut tokens = self.spp.tokenize(input);
-        tokens.push("</s>".to_owned());
-        Ok(tokens)
+        Ok(self.special_tokens.apply_source(self.tokenize(input)))
     }
 
     fn decode(&self, tokens: Vec<String>) -> anyhow::Result<String> {
-        Ok(self.spp.convert_tokens_to_string(tokens).trim().to_owned())
+        let tokens = self.special_tokens.strip(tokens);
+        let res = match &self.spp {
+            Inner::Unigram(spp) => spp.convert_tokens_to_string(tokens),
+            Inner::Bpe(spp) => spp.convert_tokens_to_string(tokens),
+        };
+        Ok(res.trim().to_owned())
+    }
+
+    fn encode_prefix(&self, input: &str) -> anyhow::Result<Vec<String>> {
+        Ok(self
+            .special_tokens
+            .apply_target_prefix(self.tokenize(input)))
+    }
+
+    /// Encodes a batch of strings in parallel.
+    fn encode_batch(&self, inputs: &[&str]) -> anyhow::Result<Vec<Vec<String>>> {
+        let tokens = match &self.spp {
+            Inner::Unigram(spp) => MultiThreadedTokenizer::tokenize_list(spp, inputs),
+            Inner::Bpe(spp) => MultiThreadedTokenizer::tokenize_list(spp, inputs),
+        };
+        Ok(tokens
+            .into_iter()
+            .map(|t| self.special_tokens.apply_source(t))
+            .collect())
     }
+
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        Box::new(SentencePieceDecoder::new(self.special_tokens.clone()))
+    }
+
+    fn encode_with_offsets(&self, input: &str) -> anyhow::Result<Vec<(String, (usize, usize))>> {
+        Ok(self
+            .special_tokens
+            .apply_source_with_offsets(self.tokenize_with_offsets(input), input.len()))
+    }
+
+    fn encode_ids(&self, input: &str) -> anyhow::Result<Vec<u32>> {
+        let vocab = self.vocab();
+        self.encode(input)?
+            .iter()
+            .map(|token| Ok(u32::try_from(vocab.token_to_id(token))?))
+            .collect()
+    }
+
+    fn decode_ids(&self, ids: &[u32]) -> anyhow::Result<String> {
+        let vocab = self.vocab();
+        let tokens = ids
+            .iter()
+            .map(|&id| vocab.id_to_token(&i64::from(id)))
+            .collect();
+        self.decode(tokens)
+    }
+
+    fn adds_language_tokens(&self) -> bool {
+        self.special_tokens.has_languages()
+    }
+}
+
+#[test]
+fn missing_model_file() {
+    assert!(matches!(
+        SentenceTokenizer::new("/nonexistent/spm.model"),
+        Err(TokenizerError::FileNotFound(_))
+    ));
+    assert_eq!("BPE".parse::<ModelType>().unwrap(), ModelType::Bpe);
+    assert!(matches!(
+        "word".parse::<ModelType>(),
+        Err(TokenizerError::UnsupportedModelType(_))
+    ));
+
+    // A piece, then a trainer spec with `model_type: BPE` after a fixed32 field.
+    let model = b"\x0a\x03\x0a\x01a\x12\x07\x25\x00\x00\x80\x3f\x18\x02";
+    assert_eq!(trainer_model_type(model), Some(2));
+    assert_eq!(trainer_model_type(&model[..5]), Some(1));
+    assert_eq!(trainer_model_type(&model[..8]), None);
 }
@@ -1,17 +1,26 @@
 use std::path::Path;
 
-use anyhow::{Error, Result};
+use anyhow::{Error, Result, anyhow};
 use sentencepiece::SentencePieceProcessor;
 
+use crate::tokenizer::streaming::SentencePieceDecoder;
+use crate::tokenizer::{SpecialTokens, StreamingDecoder, TokenizerError};
+
+fn open(path: &Path) -> Result<SentencePieceProcessor, TokenizerError> {
+    TokenizerError::check_file(path)?;
+    SentencePieceProcessor::open(path).map_err(|err| TokenizerError::parse(path, err))
+}
+
 pub struct Tokenizer {
     enc: SentencePieceProcessor,
     dec: SentencePieceProcessor,
+    special_tokens: SpecialTokens,
 }
 
 impl Tokenizer {
     /// Create a tokenizer instance by specifying the path to a directory containing `source.spm`
     /// and `target.spm`.
-    pub fn new<T: AsRef<Path>>(path: T) -> Result<Self> {
+    pub fn new<T: AsRef<Path>>(path: T) -> Result<Self, TokenizerError> {
         Tokenizer::from_file(
             path.as_ref().join("source.spm"),
             path.as_ref().join("target.spm"),
@@ -19,27 +28,83 @@ impl Tokenizer {
     }
 
     /// Create a tokenizer instance by specifying the path to `source.spm` and `target.spm`.
-    pub fn from_file<T: AsRef<Path>, U: AsRef<Path>>(src: T, target: U) -> Result<Self> {
+    pub fn from_file<T: AsRef<Path>, U: AsRef<Path>>(
+        src: T,
+        target: U,
+    ) -> Result<Self, TokenizerError> {
         Ok(Self {
-            enc: SentencePieceProcessor::open(src)?,
-            dec: SentencePieceProcessor::open(target)?,
+            enc: open(src.as_ref())?,
+            dec: open(target.as_ref())?,
+            special_tokens: SpecialTokens::default(),
         })
     }
+
+    /// Sets the special tokens added when encoding and removed when decoding.
+    pub fn with_special_tokens(&mut self, special_tokens: SpecialTokens) -> &mut Self {
+        self.special_tokens = special_tokens;
+        self
+    }
+
+    fn pieces(processor: &SentencePieceProcessor, input: &str) -> Result<Vec<String>> {
+        Ok(processor
+            .encode(input)?
+            .iter()
+            .map(|v| v.piece.to_string())
+            .collect())
+    }
 }
 
 impl crate::Tokenizer for Tokenizer {
     fn encode(&self, input: &str) -> Result<Vec<String>> {
-        let mut source: Vec<String> = self
+        Ok(self
+            .special_tokens
+            .apply_source(Self::pieces(&self.enc, input)?))
+    }
+
+    fn decode(&self, tokens: Vec<String>) -> Result<String> {
+        self.dec
+            .decode_pieces(&self.special_tokens.strip(tokens))
+            .map_err(Error::new)
+    }
+
+    fn encode_prefix(&self, input: &str) -> Result<Vec<String>> {
+        Ok(self
+            .special_tokens
+            .apply_target_prefix(Self::pieces(&self.dec, input)?))
+    }
+
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        Box::new(SentencePieceDecoder::new(self.special_tokens.clone()))
+    }
+
+    fn encode_with_offsets(&self, input: &str) -> Result<Vec<(String, (usize, usize))>> {
+        let tokens = self
             .enc
             .encode(input)?
-            .iter()
-            .map(|v| v.piece.to_string())
+            .into_iter()
+            .map(|v| (v.piece, (v.span.0 as usize, v.span.1 as usize)))
             .collect();
-        source.push("</s>".to_string());
-        Ok(source)
+        Ok(self
+            .special_tokens
+            .apply_source_with_offsets(tokens, input.len()))
     }
 
-    fn decode(&self, tokens: Vec<String>) -> Result<String> {
-        self.dec.decode_pieces(&tokens).map_err(Error::new)
+    fn encode_ids(&self, input: &str) -> Result<Vec<u32>> {
+        self.encode(input)?
+            .iter()
+            .map(|piece| {
+                self.enc
+                    .piece_to_id(piece)?
+                    .ok_or_else(|| anyhow!("piece not in the vocabulary: {piece}"))
+            })
+            .collect()
+    }
+
+    fn decode_ids(&self, ids: &[u32]) -> Result<String> {
+        self.dec.decode_piece_ids(ids).map_err(Error::new)
+    }
+
+    fn adds_language_tokens(&self) -> bool {
+        self.special_tokens.has_languages()
     }
 }
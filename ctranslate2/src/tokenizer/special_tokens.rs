@@ -0,0 +1,269 @@
+//! Configuration of the special tokens added around tokenized sentences.
+//!
+//! Models differ in how they expect their input to be framed: most Marian models only want
+//! `</s>` at the end, NLLB and M2M100 prepend a source language token, mBART appends it after
+//! `</s>`, and some models start with `<s>`. [`SpecialTokens`] describes that framing, and every
+//! tokenizer in this crate applies it in [`encode`](crate::Tokenizer::encode) and
+//! [`encode_prefix`](crate::Tokenizer::encode_prefix) and removes the special tokens again in
+//! [`decode`](crate::Tokenizer::decode).
+//!
+//! The configuration can be read from the `config.json` written by the CTranslate2 converters
+//! and from a Hugging Face `tokenizer_config.json`:
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use ctranslate2::tokenizer::SpecialTokens;
+//! use ctranslate2::tokenizer::rust_tokenizers::SentenceTokenizer;
+//!
+//! # fn main() -> Result<()> {
+//! let path = "/path/to/model";
+//! let mut t = SentenceTokenizer::new(format!("{path}/spm.model"))?;
+//! t.with_special_tokens(SpecialTokens::from_model_dir(path)?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+const CONFIG_FILE: &str = "config.json";
+const TOKENIZER_CONFIG_FILE: &str = "tokenizer_config.json";
+
+/// Where the source language token is placed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LanguageTagPosition {
+    /// Before the sentence, e.g. `eng_Latn ▁Hello </s>` for NLLB and M2M100.
+    #[default]
+    Prefix,
+    /// After the end of sentence token, e.g. `▁Hello </s> en_XX` for mBART.
+    Suffix,
+}
+
+/// The special tokens added to and removed from tokenized sentences.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SpecialTokens {
+    /// Prepend `bos_token` to the source.
+    pub add_bos: bool,
+    /// Append `eos_token` to the source.
+    pub add_eos: bool,
+    /// The beginning of sentence token.
+    pub bos_token: String,
+    /// The end of sentence token.
+    pub eos_token: String,
+    /// The padding token.
+    pub pad_token: String,
+    /// Further tokens that are removed when decoding.
+    pub additional: Vec<String>,
+    /// The language token added to the source.
+    pub source_lang: Option<String>,
+    /// The language token forced at the start of the target.
+    pub target_lang: Option<String>,
+    /// Where `source_lang` is placed.
+    pub lang_position: LanguageTagPosition,
+}
+
+impl Default for SpecialTokens {
+    fn default() -> Self {
+        Self {
+            add_bos: false,
+            add_eos: true,
+            bos_token: "<s>".to_owned(),
+            eos_token: "</s>".to_owned(),
+            pad_token: "<pad>".to_owned(),
+            additional: Vec::new(),
+            source_lang: None,
+            target_lang: None,
+            lang_position: LanguageTagPosition::Prefix,
+        }
+    }
+}
+
+impl SpecialTokens {
+    /// Reads the configuration from `config.json` and `tokenizer_config.json` in the given
+    /// model directory. Missing files and keys keep their default values.
+    ///
+    /// From `config.json`, `bos_token` and `eos_token` are read, and `add_source_bos` and
+    /// `add_source_eos` disable adding the tokens in the tokenizer, since CTranslate2 then adds
+    /// them itself. From `tokenizer_config.json`, `bos_token`, `eos_token`, `pad_token`,
+    /// `add_bos_token`, `add_eos_token`, `additional_special_tokens`, `src_lang` and `tgt_lang`
+    /// are read.
+    pub fn from_model_dir<P: AsRef<Path>>(model_dir: P) -> Result<Self> {
+        let mut res = Self::default();
+
+        if let Some(config) = read_json(&model_dir.as_ref().join(CONFIG_FILE))? {
+            res.update_from_ct2_config(&config);
+        }
+        if let Some(config) = read_json(&model_dir.as_ref().join(TOKENIZER_CONFIG_FILE))? {
+            res.update_from_tokenizer_config(&config);
+        }
+        Ok(res)
+    }
+
+    /// Applies the settings found in a CTranslate2 `config.json`.
+    pub fn update_from_ct2_config(&mut self, config: &Value) {
+        if let Some(token) = token_value(config, "bos_token") {
+            self.bos_token = token;
+        }
+        if let Some(token) = token_value(config, "eos_token") {
+            self.eos_token = token;
+        }
+        if config["add_source_bos"].as_bool() == Some(true) {
+            self.add_bos = false;
+        }
+        if config["add_source_eos"].as_bool() == Some(true) {
+            self.add_eos = false;
+        }
+    }
+
+    /// Applies the settings found in a Hugging Face `tokenizer_config.json`.
+    pub fn update_from_tokenizer_config(&mut self, config: &Value) {
+        if let Some(token) = token_value(config, "bos_token") {
+            self.bos_token = token;
+        }
+        if let Some(token) = token_value(config, "eos_token") {
+            self.eos_token = token;
+        }
+        if let Some(token) = token_value(config, "pad_token") {
+            self.pad_token = token;
+        }
+        if let Some(add) = config["add_bos_token"].as_bool() {
+            self.add_bos = add;
+        }
+        if let Some(add) = config["add_eos_token"].as_bool() {
+            self.add_eos = add;
+        }
+        if let Some(tokens) = config["additional_special_tokens"].as_array() {
+            self.additional = tokens
+                .iter()
+                .filter_map(|v| match v {
+                    Value::String(s) => Some(s.clone()),
+                    v => v["content"].as_str().map(str::to_owned),
+                })
+                .collect();
+        }
+        if let Some(lang) = config["src_lang"].as_str() {
+            self.source_lang = Some(lang.to_owned());
+        }
+        if let Some(lang) = config["tgt_lang"].as_str() {
+            self.target_lang = Some(lang.to_owned());
+        }
+    }
+
+    /// Frames tokenized source text with the configured special tokens.
+    pub fn apply_source(&self, tokens: Vec<String>) -> Vec<String> {
+        let mut res = Vec::with_capacity(tokens.len() + 3);
+        if self.lang_position == LanguageTagPosition::Prefix {
+            res.extend(self.source_lang.clone());
+        }
+        if self.add_bos {
+            res.push(self.bos_token.clone());
+        }
+        res.extend(tokens);
+        if self.add_eos {
+            res.push(self.eos_token.clone());
+        }
+        if self.lang_position == LanguageTagPosition::Suffix {
+            res.extend(self.source_lang.clone());
+        }
+        res
+    }
+
+    /// Frames tokenized source text like [`apply_source`](Self::apply_source), keeping the byte
+    /// span of each token. The added tokens get an empty span at the start or the end of the
+    /// input of length `input_len`.
+    pub fn apply_source_with_offsets(
+        &self,
+        tokens: Vec<(String, (usize, usize))>,
+        input_len: usize,
+    ) -> Vec<(String, (usize, usize))> {
+        let at_start = |t: &String| (t.clone(), (0, 0));
+        let at_end = |t: &String| (t.clone(), (input_len, input_len));
+
+        let mut res = Vec::with_capacity(tokens.len() + 3);
+        if self.lang_position == LanguageTagPosition::Prefix {
+            res.extend(self.source_lang.iter().map(at_start));
+        }
+        if self.add_bos {
+            res.push(at_start(&self.bos_token));
+        }
+        res.extend(tokens);
+        if self.add_eos {
+            res.push(at_end(&self.eos_token));
+        }
+        if self.lang_position == LanguageTagPosition::Suffix {
+            res.extend(self.source_lang.iter().map(at_end));
+        }
+        res
+    }
+
+    /// Prepends the target language token, if any, to a tokenized target prefix.
+    pub fn apply_target_prefix(&self, tokens: Vec<String>) -> Vec<String> {
+        self.target_lang.iter().cloned().chain(tokens).collect()
+    }
+
+    /// Returns whether a source or target language token is configured.
+    pub fn has_languages(&self) -> bool {
+        self.source_lang.is_some() || self.target_lang.is_some()
+    }
+
+    /// Returns whether the token is one of the configured special tokens.
+    pub fn is_special(&self, token: &str) -> bool {
+        token == self.bos_token
+            || token == self.eos_token
+            || token == self.pad_token
+            || self.source_lang.as_deref() == Some(token)
+            || self.target_lang.as_deref() == Some(token)
+            || self.additional.iter().any(|t| t == token)
+    }
+
+    /// Removes all special tokens.
+    pub fn strip(&self, tokens: Vec<String>) -> Vec<String> {
+        tokens.into_iter().filter(|t| !self.is_special(t)).collect()
+    }
+}
+
+fn read_json(path: &Path) -> Result<Option<Value>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|err| anyhow!("failed to read {}: {err}", path.display()))?;
+    serde_json::from_str(&content)
+        .map(Some)
+        .map_err(|err| anyhow!("failed to parse {}: {err}", path.display()))
+}
+
+/// Reads a token given either as a string or as an `AddedToken` object.
+fn token_value(config: &Value, key: &str) -> Option<String> {
+    match &config[key] {
+        Value::String(s) => Some(s.clone()),
+        v => v["content"].as_str().map(str::to_owned),
+    }
+}
+
+#[test]
+fn special_tokens_from_configs() {
+    let mut st = SpecialTokens::default();
+    st.update_from_ct2_config(&serde_json::json!({"add_source_eos": true, "eos_token": "</e>"}));
+    st.update_from_tokenizer_config(&serde_json::json!({
+        "bos_token": {"content": "<b>"},
+        "add_bos_token": true,
+        "src_lang": "eng_Latn",
+        "tgt_lang": "deu_Latn",
+    }));
+
+    let tokens = vec!["▁Hi".to_owned()];
+    assert_eq!(st.apply_source(tokens.clone()), ["eng_Latn", "<b>", "▁Hi"]);
+    assert_eq!(st.apply_target_prefix(tokens.clone()), ["deu_Latn", "▁Hi"]);
+    assert_eq!(
+        st.strip(vec![
+            "deu_Latn".to_owned(),
+            "▁Hi".to_owned(),
+            "</e>".to_owned()
+        ]),
+        tokens
+    );
+}
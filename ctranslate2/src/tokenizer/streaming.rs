@@ -0,0 +1,275 @@
+//! Incremental detokenization of generated tokens.
+//!
+//! A [`StreamingDecoder`] receives the target tokens one at a time and returns only the text
+//! completed by each token, so it can be shown while the model is still generating. Every
+//! tokenizer returns a suitable decoder from
+//! [`Tokenizer::streaming_decoder`](crate::Tokenizer::streaming_decoder):
+//!
+//! - [`SentencePieceDecoder`] for SentencePiece models: `▁` starts a new word, and
+//!   byte-fallback tokens such as `<0xE2>` are buffered until they form a complete UTF-8
+//!   character.
+//! - [`IncrementalDecoder`] for any other tokenizer: the tokens received so far are decoded
+//!   again and the new suffix is returned. Text ending with an incomplete character, e.g. a
+//!   multi-byte character split across byte-level BPE tokens, is held back.
+//! - [`WordDecoder`] for tokenizers joining words with spaces.
+//! - The OpenNMT tokenizer places the spaces according to the joiners (`￭`) or spacers of the
+//!   tokens, and the Moses and subword-nmt tokenizers detokenize every word once its last
+//!   subword, without the `@@` separator, is received.
+//!
+//! ```
+//! use ctranslate2::Tokenizer;
+//! use ctranslate2::tokenizer::whitespace;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let t = whitespace::Tokenizer::new();
+//! let mut decoder = t.streaming_decoder();
+//! let mut text = String::new();
+//! for token in ["Hello", "world", "</s>"] {
+//!     text += &decoder.push(token)?;
+//! }
+//! text += &decoder.finish()?;
+//! assert_eq!(text, "Hello world");
+//! # Ok(())
+//! # }
+//! ```
+
+use anyhow::Result;
+
+use crate::Tokenizer;
+use crate::tokenizer::SpecialTokens;
+
+/// Decodes target tokens one at a time.
+pub trait StreamingDecoder: Send {
+    /// Adds the next token and returns the text it completes, which may be empty.
+    fn push(&mut self, token: &str) -> Result<String>;
+
+    /// Returns the text still held back once all tokens have been pushed.
+    fn finish(&mut self) -> Result<String>;
+}
+
+/// Bytes waiting to form complete UTF-8 characters.
+#[derive(Clone, Debug, Default)]
+struct Utf8Buffer {
+    bytes: Vec<u8>,
+}
+
+impl Utf8Buffer {
+    /// Appends a byte and returns the characters completed so far. Invalid sequences are
+    /// replaced with U+FFFD.
+    fn push(&mut self, byte: u8) -> String {
+        self.bytes.push(byte);
+        let mut res = String::new();
+        loop {
+            match std::str::from_utf8(&self.bytes) {
+                Ok(s) => {
+                    res.push_str(s);
+                    self.bytes.clear();
+                    return res;
+                }
+                Err(err) => {
+                    let valid = err.valid_up_to();
+                    // Cannot fail: the bytes up to `valid` were just validated.
+                    res.push_str(std::str::from_utf8(&self.bytes[..valid]).unwrap_or_default());
+                    match err.error_len() {
+                        // An incomplete character at the end: wait for more bytes.
+                        None => {
+                            self.bytes.drain(..valid);
+                            return res;
+                        }
+                        Some(len) => {
+                            res.push(char::REPLACEMENT_CHARACTER);
+                            self.bytes.drain(..valid + len);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the pending bytes, replacing an incomplete character with U+FFFD.
+    fn flush(&mut self) -> String {
+        let res = String::from_utf8_lossy(&self.bytes).into_owned();
+        self.bytes.clear();
+        res
+    }
+}
+
+/// Parses a SentencePiece byte-fallback token such as `<0xE2>`.
+fn byte_fallback(token: &str) -> Option<u8> {
+    let hex = token.strip_prefix("<0x")?.strip_suffix('>')?;
+    if hex.len() != 2 {
+        return None;
+    }
+    u8::from_str_radix(hex, 16).ok()
+}
+
+/// Streaming decoder for SentencePiece pieces.
+#[derive(Clone, Debug)]
+pub struct SentencePieceDecoder {
+    special_tokens: SpecialTokens,
+    pending: Utf8Buffer,
+    started: bool,
+}
+
+impl SentencePieceDecoder {
+    /// Creates a decoder skipping the given special tokens.
+    pub fn new(special_tokens: SpecialTokens) -> Self {
+        Self {
+            special_tokens,
+            pending: Utf8Buffer::default(),
+            started: false,
+        }
+    }
+
+    /// Removes the space of the first word, as SentencePiece does.
+    fn emit(&mut self, text: String) -> String {
+        if self.started {
+            return text;
+        }
+        let text = text.trim_start_matches(' ').to_owned();
+        self.started = !text.is_empty();
+        text
+    }
+}
+
+impl StreamingDecoder for SentencePieceDecoder {
+    fn push(&mut self, token: &str) -> Result<String> {
+        if self.special_tokens.is_special(token) {
+            return Ok(String::new());
+        }
+        if let Some(byte) = byte_fallback(token) {
+            let text = self.pending.push(byte);
+            return Ok(self.emit(text));
+        }
+        let text = self.pending.flush() + &token.replace('▁', " ");
+        Ok(self.emit(text))
+    }
+
+    fn finish(&mut self) -> Result<String> {
+        let text = self.pending.flush();
+        Ok(self.emit(text))
+    }
+}
+
+/// Streaming decoder for tokenizers joining words with a space.
+#[derive(Clone, Debug)]
+pub struct WordDecoder {
+    special_tokens: SpecialTokens,
+    started: bool,
+}
+
+impl WordDecoder {
+    /// Creates a decoder skipping the given special tokens.
+    pub fn new(special_tokens: SpecialTokens) -> Self {
+        Self {
+            special_tokens,
+            started: false,
+        }
+    }
+}
+
+impl StreamingDecoder for WordDecoder {
+    fn push(&mut self, token: &str) -> Result<String> {
+        if self.special_tokens.is_special(token) {
+            return Ok(String::new());
+        }
+        if std::mem::replace(&mut self.started, true) {
+            Ok(format!(" {token}"))
+        } else {
+            Ok(token.to_owned())
+        }
+    }
+
+    fn finish(&mut self) -> Result<String> {
+        Ok(String::new())
+    }
+}
+
+/// Streaming decoder built on [`Tokenizer::decode`].
+///
+/// Each call decodes all tokens received so far and returns the text after what was already
+/// returned. This works with any decoder, at the cost of decoding the sequence again for
+/// every token.
+pub struct IncrementalDecoder<'a, T: ?Sized> {
+    tokenizer: &'a T,
+    tokens: Vec<String>,
+    emitted: usize,
+}
+
+impl<'a, T: Tokenizer + ?Sized> IncrementalDecoder<'a, T> {
+    /// Creates a decoder using the given tokenizer.
+    pub fn new(tokenizer: &'a T) -> Self {
+        Self {
+            tokenizer,
+            tokens: Vec::new(),
+            emitted: 0,
+        }
+    }
+
+    fn next_text(&mut self, hold_incomplete: bool) -> Result<String> {
+        let text = self.tokenizer.decode(self.tokens.clone())?;
+        if hold_incomplete && text.ends_with(char::REPLACEMENT_CHARACTER) {
+            return Ok(String::new());
+        }
+        // Decoders may rewrite the end of the text, e.g. trim a trailing space, so only
+        // the part after what was already returned is new. If the rewritten text no longer
+        // has a character boundary there, the character containing it is returned again.
+        let mut start = self.emitted.min(text.len());
+        while !text.is_char_boundary(start) {
+            start -= 1;
+        }
+        let res = text[start..].to_owned();
+        self.emitted = self.emitted.max(text.len());
+        Ok(res)
+    }
+}
+
+impl<T: Tokenizer + ?Sized> StreamingDecoder for IncrementalDecoder<'_, T> {
+    fn push(&mut self, token: &str) -> Result<String> {
+        self.tokens.push(token.to_owned());
+        self.next_text(true)
+    }
+
+    fn finish(&mut self) -> Result<String> {
+        self.next_text(false)
+    }
+}
+
+#[test]
+fn stream_sentencepiece_pieces() {
+    let mut decoder = SentencePieceDecoder::new(SpecialTokens::default());
+    let res = ["▁Pri", "ce", "▁", "<0xE2>", "<0x82>", "<0xAC>", "5", "</s>"]
+        .into_iter()
+        .map(|t| decoder.push(t).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(res, ["Pri", "ce", " ", "", "", "€", "5", ""]);
+
+    decoder.push("<0xC3>").unwrap();
+    assert_eq!(decoder.finish().unwrap(), "\u{FFFD}");
+
+    let t = crate::tokenizer::whitespace::Tokenizer::new();
+    let mut decoder = IncrementalDecoder::new(&t);
+    assert_eq!(decoder.push("a").unwrap(), "a");
+    assert_eq!(decoder.push("b").unwrap(), " b");
+    assert_eq!(decoder.push("</s>").unwrap(), "");
+
+    // A decoder rewriting the end of the text, here `a` into the two-byte `é`.
+    struct Rewriting;
+
+    impl Tokenizer for Rewriting {
+        fn encode(&self, input: &str) -> Result<Vec<String>> {
+            Ok(vec![input.to_owned()])
+        }
+
+        fn decode(&self, tokens: Vec<String>) -> Result<String> {
+            Ok(match tokens.len() {
+                1 => "a".to_owned(),
+                _ => "éx".to_owned(),
+            })
+        }
+    }
+
+    let mut decoder = IncrementalDecoder::new(&Rewriting);
+    assert_eq!(decoder.push("a").unwrap(), "a");
+    assert_eq!(decoder.push("b").unwrap(), "éx");
+}
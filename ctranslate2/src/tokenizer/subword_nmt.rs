@@ -0,0 +1,191 @@
+//! The preprocessing of classic Marian and Transformer models: Moses tokenization followed by
+//! [subword-nmt](https://github.com/rsennrich/subword-nmt) BPE.
+//!
+//! Every subword but the last of a word ends with the `@@` separator, e.g. `low@@ er`. Decoding
+//! joins the subwords again and detokenizes the words with Moses.
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! use ctranslate2::Translator2;
+//! use ctranslate2::tokenizer::{moses, subword_nmt};
+//!
+//! # fn main() -> Result<()> {
+//! let path = "/path/to/model";
+//! let t = Translator2::with_tokenizers(
+//!     path,
+//!     &Default::default(),
+//!     subword_nmt::Tokenizer::new(moses::Tokenizer::new("en"), format!("{path}/codes"))?,
+//!     subword_nmt::Tokenizer::new(moses::Tokenizer::new("de"), format!("{path}/codes"))?,
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::tokenizer::bpe_codes::BpeCodes;
+use crate::tokenizer::{SpecialTokens, StreamingDecoder, TokenizerError, moses};
+
+/// The default subword separator.
+pub const SEPARATOR: &str = "@@";
+
+/// Moses tokenization followed by subword-nmt BPE.
+pub struct Tokenizer {
+    moses: moses::Tokenizer,
+    codes: BpeCodes,
+    separator: String,
+    special_tokens: SpecialTokens,
+}
+
+impl Tokenizer {
+    /// Create a tokenizer from a Moses tokenizer and the path to a subword-nmt `codes` file.
+    pub fn new<P: AsRef<Path>>(moses: moses::Tokenizer, codes: P) -> Result<Self, TokenizerError> {
+        Ok(Self::with_codes(moses, BpeCodes::from_file(codes)?))
+    }
+
+    /// Create a tokenizer from a Moses tokenizer and parsed BPE codes.
+    pub fn with_codes(moses: moses::Tokenizer, codes: BpeCodes) -> Self {
+        Self {
+            moses,
+            codes,
+            separator: SEPARATOR.to_owned(),
+            special_tokens: SpecialTokens::default(),
+        }
+    }
+
+    /// Sets the separator ending non-final subwords, `@@` by default.
+    pub fn with_separator(&mut self, separator: &str) -> &mut Self {
+        self.separator = separator.to_owned();
+        self
+    }
+
+    /// Sets the special tokens added when encoding and removed when decoding.
+    pub fn with_special_tokens(&mut self, special_tokens: SpecialTokens) -> &mut Self {
+        self.special_tokens = special_tokens;
+        self
+    }
+
+    /// Tokenizes the text with Moses and splits the words into subwords.
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        self.tokenize_with_offsets(text)
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect()
+    }
+
+    /// Tokenizes the text like [`tokenize`](Self::tokenize), keeping the byte span of the input
+    /// every subword comes from.
+    pub fn tokenize_with_offsets(&self, text: &str) -> Vec<(String, (usize, usize))> {
+        let mut res = Vec::new();
+        for word in self.moses.tokenize_spanned(text) {
+            let pieces = self
+                .codes
+                .apply(&word.iter().map(|&(c, _)| c).collect::<String>());
+            let mut rest = word.as_slice();
+            for (i, mut piece) in pieces.iter().cloned().enumerate() {
+                let (chars, tail) = rest.split_at(piece.chars().count().min(rest.len()));
+                rest = tail;
+                if i + 1 < pieces.len() {
+                    piece.push_str(&self.separator);
+                }
+                res.push((piece, moses::span_of(chars)));
+            }
+        }
+        res
+    }
+
+    /// Joins the subwords and detokenizes the words with Moses.
+    pub fn detokenize<S: AsRef<str>>(&self, tokens: &[S]) -> String {
+        let mut words = Vec::<String>::new();
+        let mut continued = false;
+        for token in tokens {
+            let token = token.as_ref();
+            let (piece, next_continued) = match token.strip_suffix(&self.separator) {
+                Some(piece) => (piece, true),
+                None => (token, false),
+            };
+            match words.last_mut() {
+                Some(word) if continued => word.push_str(piece),
+                _ => words.push(piece.to_owned()),
+            }
+            continued = next_continued;
+        }
+        self.moses.detokenize(&words)
+    }
+}
+
+impl crate::Tokenizer for Tokenizer {
+    fn encode(&self, input: &str) -> Result<Vec<String>> {
+        Ok(self.special_tokens.apply_source(self.tokenize(input)))
+    }
+
+    fn decode(&self, tokens: Vec<String>) -> Result<String> {
+        Ok(self.detokenize(&self.special_tokens.strip(tokens)))
+    }
+
+    fn encode_prefix(&self, input: &str) -> Result<Vec<String>> {
+        Ok(self
+            .special_tokens
+            .apply_target_prefix(self.tokenize(input)))
+    }
+
+    fn encode_with_offsets(&self, input: &str) -> Result<Vec<(String, (usize, usize))>> {
+        Ok(self
+            .special_tokens
+            .apply_source_with_offsets(self.tokenize_with_offsets(input), input.len()))
+    }
+
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        Box::new(moses::Decoder::new(
+            &self.moses,
+            &self.special_tokens,
+            Some(&self.separator),
+        ))
+    }
+
+    fn adds_language_tokens(&self) -> bool {
+        self.special_tokens.has_languages()
+    }
+}
+
+#[test]
+fn subword_nmt_round_trip() {
+    use crate::Tokenizer as _;
+
+    let codes = BpeCodes::parse("#version: 0.2\nl o\nlo w\ne r</w>\n").unwrap();
+    let t = Tokenizer::with_codes(moses::Tokenizer::new("en"), codes);
+    let tokens = t.encode("Lower, lower!").unwrap();
+    assert_eq!(
+        tokens,
+        ["L@@", "o@@", "w@@", "er", ",", "low@@", "er", "!", "</s>"]
+    );
+    assert_eq!(t.decode(tokens.clone()).unwrap(), "Lower, lower!");
+    let mut decoder = t.streaming_decoder();
+    let streamed = tokens
+        .iter()
+        .map(|token| decoder.push(token).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(streamed, ["", "", "", "Lower", ",", "", " lower", "!", ""]);
+
+    let tokens = t.encode_with_offsets("lower & Lower").unwrap();
+    assert_eq!(
+        tokens,
+        [
+            ("low@@", (0, 3)),
+            ("er", (3, 5)),
+            ("&@@", (6, 7)),
+            ("a@@", (6, 7)),
+            ("m@@", (6, 7)),
+            ("p@@", (6, 7)),
+            (";", (6, 7)),
+            ("L@@", (8, 9)),
+            ("o@@", (9, 10)),
+            ("w@@", (10, 11)),
+            ("er", (11, 13)),
+            ("</s>", (13, 13)),
+        ]
+        .map(|(t, span)| (t.to_owned(), span))
+    );
+}
@@ -0,0 +1,71 @@
+//! A tokenizer splitting on whitespace.
+//!
+//! It does not need any model file, which makes it a convenient companion of
+//! [`MockBackend`](crate::backend::mock::MockBackend) in tests.
+
+use anyhow::Result;
+
+use crate::tokenizer::streaming::WordDecoder;
+use crate::tokenizer::{SpecialTokens, StreamingDecoder};
+
+/// Tokenizer splitting the input on Unicode whitespace. By default, `</s>` is appended.
+#[derive(Clone, Debug, Default)]
+pub struct Tokenizer {
+    special_tokens: SpecialTokens,
+}
+
+impl Tokenizer {
+    /// Create a whitespace tokenizer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the special tokens added when encoding and removed when decoding.
+    pub fn with_special_tokens(&mut self, special_tokens: SpecialTokens) -> &mut Self {
+        self.special_tokens = special_tokens;
+        self
+    }
+
+    fn split(input: &str) -> Vec<String> {
+        input.split_whitespace().map(str::to_owned).collect()
+    }
+
+    fn split_with_offsets(input: &str) -> Vec<(String, (usize, usize))> {
+        input
+            .split_whitespace()
+            .map(|word| {
+                // `word` is a subslice of `input`.
+                let start = word.as_ptr() as usize - input.as_ptr() as usize;
+                (word.to_owned(), (start, start + word.len()))
+            })
+            .collect()
+    }
+}
+
+impl crate::Tokenizer for Tokenizer {
+    fn encode(&self, input: &str) -> Result<Vec<String>> {
+        Ok(self.special_tokens.apply_source(Self::split(input)))
+    }
+
+    fn decode(&self, tokens: Vec<String>) -> Result<String> {
+        Ok(self.special_tokens.strip(tokens).join(" "))
+    }
+
+    fn encode_prefix(&self, input: &str) -> Result<Vec<String>> {
+        Ok(self.special_tokens.apply_target_prefix(Self::split(input)))
+    }
+
+    fn encode_with_offsets(&self, input: &str) -> Result<Vec<(String, (usize, usize))>> {
+        Ok(self
+            .special_tokens
+            .apply_source_with_offsets(Self::split_with_offsets(input), input.len()))
+    }
+
+    fn streaming_decoder(&self) -> Box<dyn StreamingDecoder + '_> {
+        Box::new(WordDecoder::new(self.special_tokens.clone()))
+    }
+
+    fn adds_language_tokens(&self) -> bool {
+        self.special_tokens.has_languages()
+    }
+}
@@ -0,0 +1,59 @@
+//! Structured results returned by [`Translator2`](crate::Translator2).
+
+use std::time::Duration;
+
+use crate::glossary::GlossaryReport;
+use crate::mask::MaskReport;
+
+/// A single decoded hypothesis.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hypothesis {
+    /// The decoded text.
+    pub text: String,
+    /// The target tokens the text was decoded from.
+    pub tokens: Vec<String>,
+    /// The score of the hypothesis, if `return_scores` was set.
+    pub score: Option<f32>,
+}
+
+/// The translation of one source sentence.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Translation {
+    /// The decoded text of the best hypothesis.
+    pub text: String,
+    /// The target tokens of the best hypothesis.
+    pub tokens: Vec<String>,
+    /// The score of the best hypothesis, if `return_scores` was set.
+    pub score: Option<f32>,
+    /// The remaining hypotheses, best first, if `num_hypotheses` is greater than one.
+    pub alternatives: Vec<Hypothesis>,
+    /// The attention of the best hypothesis as a `[target_len][source_len]` matrix, if
+    /// `return_attention` was set.
+    pub attention: Option<Vec<Vec<f32>>>,
+    /// The number of source tokens passed to the model.
+    pub source_tokens: usize,
+    /// The `(start, end)` byte span of each source token in the source text, if translated
+    /// with [`translate_batch_with_spans`](crate::Translator2::translate_batch_with_spans).
+    pub source_spans: Option<Vec<(usize, usize)>>,
+    /// What happened to the masked spans, if a [`Masker`](crate::mask::Masker) is set.
+    pub mask_report: Option<MaskReport>,
+    /// The check of the glossary terms found in the source, if a
+    /// [`Glossary`](crate::glossary::Glossary) is set.
+    pub glossary_report: Option<GlossaryReport>,
+    /// The time spent translating the batch this translation belongs to.
+    pub elapsed: Duration,
+}
+
+impl Translation {
+    /// Returns the best hypothesis followed by the alternatives.
+    pub fn hypotheses(&self) -> impl Iterator<Item = Hypothesis> + '_ {
+        std::iter::once(Hypothesis {
+            text: self.text.clone(),
+            tokens: self.tokens.clone(),
+            score: self.score,
+        })
+        .chain(self.alternatives.iter().cloned())
+    }
+}
@@ -6,11 +6,13 @@ use std::{
 };
 
 use ctranslate2_sys::{
-    CTranslationOptions, CTranslationResult, CTranslator, translation_result_free,
-    translation_result_has_attention, translation_result_has_scores,
-    translation_result_num_hypotheses, translation_result_output_at,
-    translation_result_output_size, translation_result_score, translator_create,
-    translator_destroy,
+    CTranslationOptions, CTranslationResult, CTranslator, translation_result_attention_row,
+    translation_result_attention_row_size, translation_result_attention_size,
+    translation_result_free, translation_result_has_attention, translation_result_has_scores,
+    translation_result_hypothesis_at, translation_result_hypothesis_size,
+    translation_result_num_hypotheses, translation_result_num_scores, translation_result_output_at,
+    translation_result_output_size, translation_result_score, translation_result_score_at,
+    translator_create, translator_destroy,
 };
 
 use crate::{compute_type::ComputeType, device::Device};
@@ -56,6 +58,56 @@ impl TranslationResult {
             out
         }
     }
+
+    /// Returns all hypotheses, best first.
+    pub fn hypotheses(&self) -> Vec<Vec<String>> {
+        unsafe {
+            (0..translation_result_num_hypotheses(self.inner))
+                .map(|h| {
+                    (0..translation_result_hypothesis_size(self.inner, h))
+                        .map(|idx| {
+                            let ptr = translation_result_hypothesis_at(self.inner, h, idx);
+                            CStr::from_ptr(ptr).to_string_lossy().to_string()
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    }
+
+    /// Returns the score of every hypothesis. Empty unless `return_scores` was set.
+    pub fn scores(&self) -> Vec<f32> {
+        unsafe {
+            (0..translation_result_num_scores(self.inner))
+                .map(|h| translation_result_score_at(self.inner, h))
+                .collect()
+        }
+    }
+
+    /// Returns the attention of every hypothesis as `[target_len][source_len]` matrices.
+    /// Empty unless `return_attention` was set.
+    pub fn attention(&self) -> Vec<Vec<Vec<f32>>> {
+        if !self.has_attention() {
+            return Vec::new();
+        }
+        unsafe {
+            (0..translation_result_num_hypotheses(self.inner))
+                .map(|h| {
+                    (0..translation_result_attention_size(self.inner, h))
+                        .map(|row| {
+                            let len = translation_result_attention_row_size(self.inner, h, row);
+                            let ptr = translation_result_attention_row(self.inner, h, row);
+                            if len == 0 {
+                                Vec::new()
+                            } else {
+                                std::slice::from_raw_parts(ptr, len).to_vec()
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    }
 }
 
 impl Drop for TranslationResult {
@@ -203,6 +255,40 @@ impl Default for TranslationOptions {
     }
 }
 
+impl TranslationOptions {
+    /// Returns a key identifying the options that affect the translations, i.e. all but the
+    /// batching options.
+    pub(crate) fn decoding_key(&self) -> String {
+        format!(
+            "{} {:?} {:?} {:?} {:?} {} {} {:?} {:?} {} {} {} {} {} {:?} {:?} {} {} {} {} {} {} {:?} {}",
+            self.beam_size,
+            self.patience,
+            self.length_penalty,
+            self.coverage_penalty,
+            self.repetition_penalty,
+            self.no_repeat_ngram_size,
+            self.disable_unk,
+            self.suppress_sequences,
+            self.prefix_bias_beta,
+            self.return_end_token,
+            self.max_input_length,
+            self.max_decoding_length,
+            self.min_decoding_length,
+            self.sampling_topk,
+            self.sampling_topp,
+            self.sampling_temperature,
+            self.use_vmap,
+            self.num_hypotheses,
+            self.return_scores,
+            self.return_attention,
+            self.return_logits_vocab,
+            self.return_alternatives,
+            self.min_alternative_expansion_prob,
+            self.replace_unknowns,
+        )
+    }
+}
+
 fn to_c_translation_options(options: &TranslationOptions) -> CTranslationOptions {
     CTranslationOptions {
         prefix_bias_beta: options.prefix_bias_beta,
@@ -243,7 +329,11 @@ fn prepare_string<U: AsRef<str>>(tokens: &[Vec<U>]) -> Result<Vec<Vec<CString>>,
         .collect()
 }
 
-fn prepare_string_pts(c_sentences: &Vec<Vec<CString>>) -> Vec<*const *const c_char> {
+/// Returns the null-terminated token arrays of the sentences and the array of pointers to
+/// them. The token arrays must outlive the pointers.
+fn prepare_string_pts(
+    c_sentences: &[Vec<CString>],
+) -> (Vec<Vec<*const c_char>>, Vec<*const *const c_char>) {
     let c_ptrs: Vec<Vec<*const c_char>> = c_sentences
         .iter()
         .map(|sentence| {
@@ -253,7 +343,7 @@ fn prepare_string_pts(c_sentences: &Vec<Vec<CString>>) -> Vec<*const *const c_ch
         })
         .collect();
     let c_sentences_ptrs: Vec<*const *const c_char> = c_ptrs.iter().map(|s| s.as_ptr()).collect();
-    c_sentences_ptrs
+    (c_ptrs, c_sentences_ptrs)
 }
 
 impl Translator {
@@ -289,16 +379,16 @@ impl Translator {
     pub fn translate_batch2<U: AsRef<str>, V: AsRef<str>>(
         &self,
         tokens: &[Vec<U>],
-        prefixes: &Vec<Vec<V>>,
+        prefixes: &[Vec<V>],
         options: TranslationOptions,
     ) -> Result<Vec<TranslationResult>, TranslatorError> {
         let opt = to_c_translation_options(&options);
         unsafe {
             let c_sentences = prepare_string(tokens)?;
-            let c_sentences_ptrs = prepare_string_pts(&c_sentences);
+            let (_c_sentences_tokens, c_sentences_ptrs) = prepare_string_pts(&c_sentences);
 
             let c_prefixes = prepare_string(prefixes)?;
-            let c_prefixes_ptrs = prepare_string_pts(&c_prefixes);
+            let (_c_prefixes_tokens, c_prefixes_ptrs) = prepare_string_pts(&c_prefixes);
 
             let num_sentences = c_sentences_ptrs.len();
 
@@ -314,6 +404,9 @@ impl Translator {
                 options.batch_type as i32,
                 &mut out_num_translations,
             );
+            if results_ptr.is_null() {
+                return Err(TranslatorError::TranslationFailed);
+            }
             let results = take_c_results(results_ptr, out_num_translations)
                 .into_iter()
                 .map(|v| TranslationResult { inner: v })
@@ -331,7 +424,7 @@ impl Translator {
         let opt = to_c_translation_options(&options);
         unsafe {
             let c_sentences = prepare_string(tokens)?;
-            let c_sentences_ptrs = prepare_string_pts(&c_sentences);
+            let (_c_sentences_tokens, c_sentences_ptrs) = prepare_string_pts(&c_sentences);
 
             let num_sentences = c_sentences_ptrs.len();
 
@@ -349,7 +442,6 @@ impl Translator {
             if results_ptr.is_null() {
                 return Err(TranslatorError::TranslationFailed);
             }
-            panic!();
             let results = take_c_results(results_ptr, out_num_translations)
                 .into_iter()
                 .map(|v| TranslationResult { inner: v })
@@ -367,3 +459,27 @@ fn take_c_results<T>(c_results: *mut *mut T, n: usize) -> Vec<*mut T> {
         owned
     }
 }
+
+#[cfg(feature = "stub")]
+#[test]
+fn stub_translate_batch() {
+    let t = Translator::new(env!("CARGO_MANIFEST_DIR"), &Default::default()).unwrap();
+    let tokens = |s: &str| s.split(' ').map(str::to_owned).collect::<Vec<_>>();
+
+    let out = t
+        .translate_batch(&[tokens("a b </s>")], Default::default())
+        .unwrap();
+    assert_eq!(out[0].output(), tokens("a b"));
+    assert!(!out[0].has_scores());
+
+    let out = t
+        .translate_batch2(&[tokens("b </s>")], &[tokens("a")], Default::default())
+        .unwrap();
+    assert_eq!(out[0].output(), tokens("a b"));
+
+    assert!(
+        t.translate_batch(&[tokens("<stub-fail>")], Default::default())
+            .is_err()
+    );
+    assert!(Translator::new("/nonexistent/model", &Default::default()).is_err());
+}
@@ -1,13 +1,146 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::time::{Duration, Instant};
+
+use unicode_normalization::UnicodeNormalization;
 
 use crate::{
-    Tokenizer, TranslationOptions, Translator, TranslatorConfig,
-    tokenizer::rust_tokenizers::SentenceTokenizer, translator::TranslatorError,
+    Tokenizer, TranslationBackend, TranslationOptions, Translator, TranslatorConfig,
+    alignment::{self, AlignmentStrategy},
+    backend::BackendOutput,
+    cache::{self, Cache},
+    glossary::{Glossary, Steering, TermMatch},
+    mask::Masker,
+    multilingual::Languages,
+    overflow::{self, OverflowPolicy},
+    processor::{Metadata, Pipeline, Processor},
+    segment::Segmenter,
+    tokenizer::TokenizerPair,
+    translation::{Hypothesis, Translation},
+    translator::{BatchType, TranslatorError},
 };
 
-pub struct Translator2<T: Tokenizer> {
-    t: Translator,
+/// What [`Translator2::translate_batch_with_text_prefixes`] returns for each sentence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PrefixOutput {
+    /// Only the text generated after the prefix.
+    #[default]
+    Continuation,
+    /// The prefix followed by the generated text.
+    Full,
+}
+
+pub struct Translator2<T: Tokenizer, B: TranslationBackend = Translator> {
+    t: B,
     tokenizer: T,
+    languages: Option<Languages>,
+    processors: Pipeline,
+    overflow_policy: OverflowPolicy,
+    masker: Option<Masker>,
+    glossary: Option<Glossary>,
+    cache: Option<Cache>,
+    /// The fingerprint of the configuration in the cache keys, see
+    /// [`update_cache_config`](Self::update_cache_config).
+    cache_config: String,
+    deduplicate: bool,
+}
+
+/// The sources of a batch after pre-processing and masking, with what is needed to restore
+/// their translations.
+struct Prepared {
+    sources: Vec<String>,
+    metadata: Vec<Metadata>,
+    /// The masked spans of every source.
+    masks: Vec<Vec<String>>,
+    /// The glossary terms found in every source.
+    terms: Vec<Vec<TermMatch>>,
+    /// The glossary term every source starts with, if any.
+    leading_terms: Vec<Option<usize>>,
+}
+
+/// The sources of a batch, split into the chunks passed to the model.
+struct Chunks {
+    /// The tokens of every chunk.
+    tokens: Vec<Vec<String>>,
+    /// The index of the source of every chunk, and its byte range in the source.
+    ranges: Vec<(usize, (usize, usize))>,
+}
+
+impl Chunks {
+    /// Returns the target prefix of every chunk: the prefix of its source for the first chunk
+    /// of a source, and none for the others.
+    fn first_prefixes(&self, prefixes: &[Vec<String>]) -> Vec<Vec<String>> {
+        self.ranges
+            .iter()
+            .enumerate()
+            .map(|(i, &(idx, _))| {
+                let first = i == 0 || self.ranges[i - 1].0 != idx;
+                match prefixes.get(idx) {
+                    Some(prefix) if first => prefix.clone(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Joins the translations of the chunks of a source, with the whitespace found between the
+/// chunks in the source. The attention rows of a chunk are placed at the position of its
+/// source tokens. Alternatives are only kept for sources translated in a single chunk.
+fn stitch(source: &str, mut parts: Vec<((usize, usize), Translation)>) -> Translation {
+    if parts.len() <= 1 {
+        return parts.pop().map(|(_, t)| t).unwrap_or_default();
+    }
+    let source_tokens = parts.iter().map(|(_, t)| t.source_tokens).sum();
+    let mut res = Translation {
+        score: Some(0.0),
+        attention: Some(Vec::new()),
+        source_tokens,
+        source_spans: Some(Vec::new()),
+        ..Default::default()
+    };
+    let mut offset = 0;
+    let mut last = None;
+    for ((start, end), part) in parts {
+        if let Some(last) = last {
+            res.text.push_str(&source[last..start]);
+        }
+        res.text.push_str(&part.text);
+        res.tokens.extend(part.tokens);
+        res.score = res.score.zip(part.score).map(|(a, b)| a + b);
+        res.attention = res.attention.zip(part.attention).map(|(mut rows, part)| {
+            for row in part {
+                let mut padded = vec![0.0; source_tokens];
+                let len = row.len().min(source_tokens - offset);
+                padded[offset..offset + len].copy_from_slice(&row[..len]);
+                rows.push(padded);
+            }
+            rows
+        });
+        res.source_spans = res
+            .source_spans
+            .zip(part.source_spans)
+            .map(|(mut spans, part)| {
+                spans.extend(part);
+                spans
+            });
+        res.elapsed = part.elapsed;
+        offset += part.source_tokens;
+        last = Some(end);
+    }
+    res
+}
+
+/// Returns how many leading tokens of `output` belong to `prefix`.
+///
+/// The model copies the prefix verbatim, but when it does not (e.g. with `replace_unknowns`)
+/// nothing is removed, so no generated token is lost.
+fn prefix_len_in(output: &[String], prefix: &[String]) -> usize {
+    if output.starts_with(prefix) {
+        prefix.len()
+    } else {
+        0
+    }
 }
 
 #[inline]
@@ -15,10 +148,8 @@ pub(crate) fn encode_all<T: Tokenizer, U: AsRef<str>>(
     tokenizer: &T,
     sources: &[U],
 ) -> anyhow::Result<Vec<Vec<String>>> {
-    sources
-        .iter()
-        .map(|s| tokenizer.encode(s.as_ref()))
-        .collect()
+    let sources = sources.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    tokenizer.encode_batch(&sources)
 }
 
 impl<T: Tokenizer> Translator2<T> {
@@ -27,67 +158,1208 @@ impl<T: Tokenizer> Translator2<T> {
         config: &TranslatorConfig,
         tokenizer: T,
     ) -> Result<Self, TranslatorError> {
-        Ok(Translator2 {
-            t: Translator::new(model_path, config)?,
+        Ok(Self::with_backend(
+            Translator::new(model_path, config)?,
             tokenizer,
+        ))
+    }
+}
+
+impl<S: Tokenizer, T: Tokenizer> Translator2<TokenizerPair<S, T>> {
+    /// Creates a translator encoding sources with `source` and decoding translations with
+    /// `target`, for models shipping distinct source and target vocabularies.
+    pub fn with_tokenizers<P: AsRef<Path>>(
+        model_path: P,
+        config: &TranslatorConfig,
+        source: S,
+        target: T,
+    ) -> Result<Self, TranslatorError> {
+        Self::new(model_path, config, TokenizerPair::new(source, target))
+    }
+}
+
+impl<T: Tokenizer, B: TranslationBackend> Translator2<T, B> {
+    /// Creates a translator running on the given backend, e.g. a
+    /// [`MockBackend`](crate::backend::mock::MockBackend) in tests.
+    pub fn with_backend(backend: B, tokenizer: T) -> Self {
+        let mut res = Translator2 {
+            t: backend,
+            tokenizer,
+            languages: None,
+            processors: Pipeline::new(),
+            overflow_policy: OverflowPolicy::default(),
+            masker: None,
+            glossary: None,
+            cache: None,
+            cache_config: String::new(),
+            deduplicate: true,
+        };
+        res.update_cache_config();
+        res
+    }
+
+    /// Returns the backend.
+    pub fn backend(&self) -> &B {
+        &self.t
+    }
+
+    /// Returns the tokenizer.
+    pub fn tokenizer(&self) -> &T {
+        &self.tokenizer
+    }
+
+    /// Sets the model family and vocabulary used by
+    /// [`translate_batch_to`](Self::translate_batch_to).
+    pub fn with_languages(&mut self, languages: Languages) -> &mut Self {
+        self.languages = Some(languages);
+        self
+    }
+
+    /// Appends a processor run on the sources before they are encoded and on the translations
+    /// after they are decoded. See [`processor`](crate::processor).
+    pub fn with_processor<P: Processor + 'static>(&mut self, processor: P) -> &mut Self {
+        self.processors.push(processor);
+        self.update_cache_config();
+        self
+    }
+
+    /// Sets what to do with sources longer than
+    /// [`TranslationOptions::max_input_length`] tokens. See [`overflow`](crate::overflow).
+    pub fn with_overflow_policy(&mut self, policy: OverflowPolicy) -> &mut Self {
+        self.overflow_policy = policy;
+        self.update_cache_config();
+        self
+    }
+
+    /// Sets the masker replacing markup, placeholders and URLs with sentinels after the
+    /// processors run on the sources, and restoring them before the processors run on the
+    /// translations. See [`mask`](crate::mask).
+    ///
+    /// Fails if the tokenizer does not preserve the sentinels, e.g. encodes them as unknown
+    /// tokens; set others with [`Masker::with_sentinels`].
+    pub fn with_masker(&mut self, masker: Masker) -> anyhow::Result<&mut Self> {
+        self.check_preserved(&masker.sentinel(0), |text| {
+            masker.find_sentinels(text).iter().any(|&(idx, _)| idx == 0)
+        })?;
+        self.masker = Some(masker);
+        self.update_cache_config();
+        Ok(self)
+    }
+
+    /// Sets the glossary whose terms are found in the sources, enforced as set by its
+    /// [`Steering`] and checked in the translations. See [`glossary`](crate::glossary).
+    ///
+    /// Fails with [`Steering::Placeholder`] if the tokenizer does not preserve the
+    /// placeholders, e.g. encodes them as unknown tokens.
+    pub fn with_glossary(&mut self, glossary: Glossary) -> anyhow::Result<&mut Self> {
+        if glossary.steering() == Steering::Placeholder {
+            self.check_preserved(&glossary.placeholder(0), |text| {
+                glossary.has_placeholder(text, 0)
+            })?;
+        }
+        self.glossary = Some(glossary);
+        self.update_cache_config();
+        Ok(self)
+    }
+
+    /// Returns an error unless `found` finds `marker` in the text decoded from its tokens.
+    fn check_preserved(&self, marker: &str, found: impl Fn(&str) -> bool) -> anyhow::Result<()> {
+        let decoded = self.tokenizer.decode(self.tokenizer.encode(marker)?)?;
+        if !found(&decoded) {
+            anyhow::bail!("the tokenizer does not preserve {marker:?}, decoded as {decoded:?}");
+        }
+        Ok(())
+    }
+
+    /// Sets the cache serving the translations of [`translate_batch`](Self::translate_batch).
+    /// See [`cache`](crate::cache).
+    pub fn with_cache(&mut self, cache: Cache) -> &mut Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Returns the cache, e.g. to read its hit and miss counters.
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    /// Sets whether [`translate_batch`](Self::translate_batch) translates identical sources
+    /// once, enabled by default. Sources are identical if they are equal after NFC
+    /// normalization. Every copy is translated independently when sampling (`sampling_topk`
+    /// other than 1).
+    pub fn with_deduplication(&mut self, deduplicate: bool) -> &mut Self {
+        self.deduplicate = deduplicate;
+        self
+    }
+
+    /// Runs the processors, the glossary and the masker on the sources.
+    fn preprocess<U: AsRef<str>>(&self, sources: &[U]) -> anyhow::Result<Prepared> {
+        let mut metadata = vec![Metadata::new(); sources.len()];
+        let mut masks = vec![Vec::new(); sources.len()];
+        let mut terms = vec![Vec::new(); sources.len()];
+        let mut leading_terms = vec![None; sources.len()];
+        let sources = sources
+            .iter()
+            .zip(&mut metadata)
+            .zip(&mut masks)
+            .zip(terms.iter_mut().zip(&mut leading_terms))
+            .map(|(((s, m), spans), (terms, leading))| {
+                let mut source = self.processors.preprocess(s.as_ref().to_owned(), m)?;
+                if let Some(glossary) = &self.glossary {
+                    *terms = glossary.find(&source);
+                    let start = source.len() - source.trim_start().len();
+                    *leading = terms.first().filter(|t| t.start == start).map(|t| t.term);
+                    if glossary.steering() == Steering::Placeholder {
+                        source = glossary.substitute(&source, terms);
+                    }
+                }
+                Ok(match &self.masker {
+                    Some(masker) => {
+                        let (masked, masked_spans) = masker.mask(&source);
+                        *spans = masked_spans;
+                        masked
+                    }
+                    None => source,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Prepared {
+            sources,
+            metadata,
+            masks,
+            terms,
+            leading_terms,
         })
     }
 
+    /// Returns the target terms forced as target prefixes of the sources starting with a
+    /// glossary term, if the glossary steers with [`Steering::Prefix`].
+    fn glossary_prefixes(&self, prepared: &Prepared) -> anyhow::Result<Option<Vec<Vec<String>>>> {
+        let Some(glossary) = &self.glossary else {
+            return Ok(None);
+        };
+        if glossary.steering() != Steering::Prefix
+            || prepared.leading_terms.iter().all(Option::is_none)
+        {
+            return Ok(None);
+        }
+        prepared
+            .leading_terms
+            .iter()
+            .map(|term| match term {
+                Some(term) => self
+                    .tokenizer
+                    .encode_prefix(&glossary.terms()[*term].target),
+                None => Ok(Vec::new()),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map(Some)
+    }
+
+    /// Translates a batch of sentences.
+    ///
+    /// If a cache is set, the sentences found in it are not translated again.
     pub fn translate_batch(
         &self,
         sources: &[String],
         options: TranslationOptions,
-    ) -> anyhow::Result<Vec<(String, f32)>> {
-        let out = self
-            .t
-            .translate_batch(&encode_all(&self.tokenizer, sources)?, options)?;
-        let mut res = Vec::new();
-        for r in out.into_iter() {
-            let score = r.score();
-            res.push((
+    ) -> anyhow::Result<Vec<Translation>> {
+        let Some(cache) = self.cache.as_ref().filter(|_| options.sampling_topk == 1) else {
+            return self.translate_uncached(sources, options);
+        };
+        let keys = sources
+            .iter()
+            .map(|s| cache.key(&self.cache_config, &options, s))
+            .collect::<Vec<_>>();
+        let mut res = keys
+            .iter()
+            .map(|k| cache.get(k))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let misses = (0..sources.len())
+            .filter(|&i| res[i].is_none())
+            .collect::<Vec<_>>();
+        if !misses.is_empty() {
+            let batch = misses
+                .iter()
+                .map(|&i| sources[i].clone())
+                .collect::<Vec<_>>();
+            for (i, translation) in misses
+                .into_iter()
+                .zip(self.translate_uncached(&batch, options)?)
+            {
+                cache.insert(keys[i].clone(), translation.clone())?;
+                res[i] = Some(translation);
+            }
+        }
+        Ok(res.into_iter().map(Option::unwrap_or_default).collect())
+    }
+
+    /// Computes the fingerprint of the configuration affecting the translations in the cache
+    /// keys: the tokenizer type, the processors, the overflow policy, the masker and the
+    /// glossary. Called by the setters of these.
+    fn update_cache_config(&mut self) {
+        let config = format!(
+            "{}\u{1f}{:?}\u{1f}{:?}\u{1f}{:?}",
+            self.processors.id(),
+            self.overflow_policy,
+            self.masker,
+            self.glossary
+        );
+        self.cache_config = format!(
+            "{}\u{1f}{}",
+            std::any::type_name::<T>(),
+            cache::fingerprint(&config)
+        );
+    }
+
+    /// Translates a batch of sentences without using the cache, translating identical
+    /// sentences once if deduplication is enabled and the decoding is not sampling.
+    fn translate_uncached(
+        &self,
+        sources: &[String],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<Translation>> {
+        if !self.deduplicate || options.sampling_topk != 1 {
+            return self.translate_distinct(sources, options);
+        }
+        let mut unique = Vec::new();
+        let mut seen = HashMap::new();
+        let positions = sources
+            .iter()
+            .map(|s| {
+                *seen.entry(s.nfc().collect::<String>()).or_insert_with(|| {
+                    unique.push(s.clone());
+                    unique.len() - 1
+                })
+            })
+            .collect::<Vec<_>>();
+        if unique.len() == sources.len() {
+            return self.translate_distinct(sources, options);
+        }
+        let res = self.translate_distinct(&unique, options)?;
+        Ok(positions.into_iter().map(|i| res[i].clone()).collect())
+    }
+
+    /// Translates a batch of sentences as is.
+    fn translate_distinct(
+        &self,
+        sources: &[String],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<Translation>> {
+        let prepared = self.preprocess(sources)?;
+        let chunks = self.encode_chunks(&prepared.sources, 0, &options)?;
+        let prefixes = self
+            .glossary_prefixes(&prepared)?
+            .map(|prefixes| chunks.first_prefixes(&prefixes));
+        let res = self.translate_tokens(
+            &chunks.tokens,
+            prefixes.as_deref(),
+            false,
+            &|_| false,
+            options,
+        )?;
+        self.finish(&prepared, &chunks, res)
+    }
+
+    /// Translates a batch of sentences like [`translate_batch`](Self::translate_batch), and
+    /// returns the byte span of each source token in
+    /// [`Translation::source_spans`], e.g. to highlight the source words matching a target
+    /// word. Requires a tokenizer implementing [`Tokenizer::encode_with_offsets`].
+    ///
+    /// If processors are set, the spans refer to the processed sources.
+    pub fn translate_batch_with_spans<U: AsRef<str>>(
+        &self,
+        sources: &[U],
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<Translation>> {
+        let prepared = self.preprocess(sources)?;
+        let sources = &prepared.sources;
+        let encoded = sources
+            .iter()
+            .map(|s| self.tokenizer.encode_with_offsets(s))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let lens = encoded.iter().map(Vec::len).collect::<Vec<_>>();
+        let ranges = self.chunk_ranges(sources, &lens, 0, &options)?;
+
+        let mut tokens = Vec::with_capacity(ranges.len());
+        let mut spans = Vec::with_capacity(ranges.len());
+        for &(idx, (start, end)) in &ranges {
+            let encoded = if (start, end) == (0, sources[idx].len()) {
+                encoded[idx].clone()
+            } else {
                 self.tokenizer
-                    .decode(r.output())
-                    .map_err(|err| anyhow::anyhow!("failed to decode: {err}"))?,
-                score,
-            ));
+                    .encode_with_offsets(&sources[idx][start..end])?
+                    .into_iter()
+                    .map(|(t, (s, e))| (t, (s + start, e + start)))
+                    .collect()
+            };
+            let (t, s): (Vec<_>, Vec<_>) = encoded.into_iter().unzip();
+            tokens.push(t);
+            spans.push(s);
+        }
+        let chunks = Chunks { tokens, ranges };
+
+        let mut res = self.translate_tokens(&chunks.tokens, None, true, &|_| false, options)?;
+        for (r, spans) in res.iter_mut().zip(spans) {
+            r.source_spans = Some(spans);
+        }
+        self.finish(&prepared, &chunks, res)
+    }
+
+    /// Returns the `(source, target)` word alignment of a translation returned by
+    /// [`translate_batch_with_spans`](Self::translate_batch_with_spans) with
+    /// `return_attention` set. See [`alignment`](crate::alignment).
+    ///
+    /// The source words are those of `source`, or of the processed source if processors are
+    /// set, and the target words those of the decoded [`Translation::tokens`].
+    pub fn align_words(
+        &self,
+        source: &str,
+        translation: &Translation,
+        strategy: AlignmentStrategy,
+    ) -> anyhow::Result<Vec<(usize, usize)>> {
+        let attention = translation.attention.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("no attention, see TranslationOptions::return_attention")
+        })?;
+        let source_spans = translation.source_spans.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("no source spans, see Translator2::translate_batch_with_spans")
+        })?;
+        let (target, target_spans) =
+            alignment::decode_with_offsets(&self.tokenizer, &translation.tokens)?;
+        Ok(alignment::to_words(
+            &alignment::align(attention, strategy),
+            &alignment::word_ids(source, source_spans),
+            &alignment::word_ids(&target, &target_spans),
+        ))
+    }
+
+    /// Translates a document sentence by sentence.
+    ///
+    /// The document is split with `segmenter`, the sentences are translated in batches of
+    /// `options.max_batch_size` sentences, or tokens with [`BatchType::Tokens`] (all at once if
+    /// it is zero), and the translations are put back in place of the sentences. A sentence
+    /// longer than `max_batch_size` tokens is translated alone. The whitespace between
+    /// sentences, including paragraph breaks, is kept as is.
+    pub fn translate_document(
+        &self,
+        document: &str,
+        segmenter: &Segmenter,
+        options: TranslationOptions,
+    ) -> anyhow::Result<String> {
+        let spans = segmenter.split_with_offsets(document);
+        let sentences = spans
+            .iter()
+            .map(|&(start, end)| document[start..end].to_owned())
+            .collect::<Vec<_>>();
+        let sizes = match options.batch_type {
+            BatchType::Examples => vec![1; sentences.len()],
+            BatchType::Tokens => encode_all(&self.tokenizer, &sentences)?
+                .iter()
+                .map(Vec::len)
+                .collect(),
+        };
+        let limit = match options.max_batch_size {
+            0 => usize::MAX,
+            n => n,
+        };
+        let mut batches = Vec::new();
+        let (mut start, mut size) = (0, 0);
+        for (i, &n) in sizes.iter().enumerate() {
+            if i > start && size + n > limit {
+                batches.push(start..i);
+                (start, size) = (i, 0);
+            }
+            size += n;
         }
+        if start < sentences.len() {
+            batches.push(start..sentences.len());
+        }
+
+        let mut res = String::with_capacity(document.len());
+        let mut last = 0;
+        let mut spans = spans.into_iter();
+        for batch in batches {
+            for translation in self.translate_batch(&sentences[batch], options.clone())? {
+                let Some((start, end)) = spans.next() else {
+                    break;
+                };
+                res.push_str(&document[last..start]);
+                res.push_str(&translation.text);
+                last = end;
+            }
+        }
+        res.push_str(&document[last..]);
         Ok(res)
     }
 
     pub fn translate_batch_with_prefixes<U, V>(
         &self,
         sources: &[U],
-        target_prefixes: &Vec<Vec<V>>,
+        target_prefixes: &[Vec<V>],
         options: TranslationOptions,
-    ) -> anyhow::Result<Vec<(String, f32)>>
+    ) -> anyhow::Result<Vec<Translation>>
     where
         U: AsRef<str>,
         V: AsRef<str>,
     {
-        let out = self.t.translate_batch2(
-            &encode_all(&self.tokenizer, sources)?,
-            target_prefixes,
+        let prefixes: Vec<Vec<String>> = target_prefixes
+            .iter()
+            .map(|p| p.iter().map(|t| t.as_ref().to_owned()).collect())
+            .collect();
+        let prepared = self.preprocess(sources)?;
+        let chunks = self.encode_chunks(&prepared.sources, 0, &options)?;
+        let res = self.translate_tokens(
+            &chunks.tokens,
+            Some(&chunks.first_prefixes(&prefixes)),
+            true,
+            &|_| false,
             options,
         )?;
-        let mut res = Vec::new();
-        for (r, prefix) in out.into_iter().zip(target_prefixes) {
-            let score = r.score();
-            let mut hypotheses = r.output();
-            hypotheses.drain(0..prefix.len());
+        self.finish(&prepared, &chunks, res)
+    }
 
-            res.push((
-                self.tokenizer
-                    .decode(hypotheses)
-                    .map_err(|err| anyhow::anyhow!("failed to decode: {err}"))?,
-                score,
-            ));
+    /// Translates a batch of sentences, forcing each translation to start with the matching
+    /// target prefix given as plain text.
+    ///
+    /// The prefixes are tokenized with [`Tokenizer::encode_prefix`], so no end of sentence
+    /// token is appended. `output` selects whether the returned text contains the prefix.
+    pub fn translate_batch_with_text_prefixes<U, V>(
+        &self,
+        sources: &[U],
+        target_prefixes: &[V],
+        output: PrefixOutput,
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<Translation>>
+    where
+        U: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let prefixes = target_prefixes
+            .iter()
+            .map(|p| self.tokenizer.encode_prefix(p.as_ref()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let prepared = self.preprocess(sources)?;
+        let chunks = self.encode_chunks(&prepared.sources, 0, &options)?;
+        let res = self.translate_tokens(
+            &chunks.tokens,
+            Some(&chunks.first_prefixes(&prefixes)),
+            output == PrefixOutput::Continuation,
+            &|_| false,
+            options,
+        )?;
+        self.finish(&prepared, &chunks, res)
+    }
+
+    /// Translates a batch of sentences from `src_lang` into `tgt_lang` with a multilingual
+    /// model such as NLLB-200, M2M100 or mBART-50.
+    ///
+    /// The language codes are those of the model family, e.g. `eng_Latn` for NLLB, `en` for
+    /// M2M100 and `en_XX` for mBART. The source language token is inserted where the family
+    /// expects it, and the target language token is forced as target prefix and removed from
+    /// the output. Requires [`with_languages`](Self::with_languages), and fails if the
+    /// tokenizer adds language tokens by itself, see [`SpecialTokens`](crate::tokenizer::SpecialTokens).
+    pub fn translate_batch_to<U: AsRef<str>>(
+        &self,
+        sources: &[U],
+        src_lang: &str,
+        tgt_lang: &str,
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<Translation>> {
+        let languages = self.languages.as_ref().ok_or_else(|| {
+            anyhow::anyhow!("no model family configured, see Translator2::with_languages")
+        })?;
+        if self.tokenizer.adds_language_tokens() {
+            anyhow::bail!("the tokenizer already adds language tokens, see SpecialTokens");
+        }
+        let src_token = languages.source_token(src_lang)?;
+        let tgt_token = languages.target_token(tgt_lang)?;
+
+        let prepared = self.preprocess(sources)?;
+        let mut chunks = self.encode_chunks(&prepared.sources, 1, &options)?;
+        chunks.tokens = chunks
+            .tokens
+            .into_iter()
+            .map(|tokens| languages.family().insert_source_token(tokens, &src_token))
+            .collect();
+        let prefixes = vec![vec![tgt_token.clone()]; chunks.tokens.len()];
+        let res = self.translate_tokens(
+            &chunks.tokens,
+            Some(&prefixes),
+            true,
+            &|t| t == tgt_token,
+            options,
+        )?;
+        self.finish(&prepared, &chunks, res)
+    }
+
+    /// Returns the byte ranges of the chunks each source is translated in, according to the
+    /// overflow policy. `lens` are the numbers of tokens of the sources, and `reserved` the
+    /// number of tokens added to every chunk after encoding.
+    fn chunk_ranges(
+        &self,
+        sources: &[String],
+        lens: &[usize],
+        reserved: usize,
+        options: &TranslationOptions,
+    ) -> anyhow::Result<Vec<(usize, (usize, usize))>> {
+        let limit = options.max_input_length;
+        let mut res = Vec::with_capacity(sources.len());
+        for (idx, (source, &len)) in sources.iter().zip(lens).enumerate() {
+            if limit == 0 || len + reserved <= limit {
+                res.push((idx, (0, source.len())));
+                continue;
+            }
+            match self.overflow_policy {
+                OverflowPolicy::Truncate => res.push((idx, (0, source.len()))),
+                OverflowPolicy::Error => anyhow::bail!(
+                    "source {idx} has {} tokens, more than max_input_length ({limit})",
+                    len + reserved
+                ),
+                OverflowPolicy::Split => {
+                    let mut fits =
+                        |s: &str| Ok(self.tokenizer.encode(s)?.len() + reserved <= limit);
+                    res.extend(
+                        overflow::split(source, &mut fits)?
+                            .into_iter()
+                            .map(|range| (idx, range)),
+                    );
+                }
+            }
         }
         Ok(res)
     }
+
+    /// Encodes the sources, split into chunks according to the overflow policy.
+    fn encode_chunks(
+        &self,
+        sources: &[String],
+        reserved: usize,
+        options: &TranslationOptions,
+    ) -> anyhow::Result<Chunks> {
+        let mut encoded = encode_all(&self.tokenizer, sources)?;
+        let lens = encoded.iter().map(Vec::len).collect::<Vec<_>>();
+        let ranges = self.chunk_ranges(sources, &lens, reserved, options)?;
+        if ranges.len() == sources.len() {
+            return Ok(Chunks {
+                tokens: encoded,
+                ranges,
+            });
+        }
+        let tokens = ranges
+            .iter()
+            .map(|&(idx, (start, end))| {
+                if (start, end) == (0, sources[idx].len()) {
+                    Ok(std::mem::take(&mut encoded[idx]))
+                } else {
+                    self.tokenizer.encode(&sources[idx][start..end])
+                }
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Chunks { tokens, ranges })
+    }
+
+    /// Joins the translations of the chunks of every source, restores their masked spans and
+    /// glossary terms, post-processes them and checks the glossary terms.
+    fn finish(
+        &self,
+        prepared: &Prepared,
+        chunks: &Chunks,
+        translations: Vec<Translation>,
+    ) -> anyhow::Result<Vec<Translation>> {
+        let mut parts = vec![Vec::new(); prepared.sources.len()];
+        for (&(idx, range), translation) in chunks.ranges.iter().zip(translations) {
+            parts[idx].push((range, translation));
+        }
+        parts
+            .into_iter()
+            .zip(&prepared.sources)
+            .zip(&prepared.metadata)
+            .zip(prepared.masks.iter().zip(&prepared.terms))
+            .map(|(((parts, source), metadata), (spans, terms))| {
+                let mut res = stitch(source, parts);
+                if let Some(masker) = &self.masker {
+                    self.unmask(masker, source, spans, &mut res)?;
+                }
+                if let Some(glossary) = &self.glossary {
+                    res.text = glossary.restore(&res.text);
+                    for h in &mut res.alternatives {
+                        h.text = glossary.restore(&h.text);
+                    }
+                }
+                res.text = self.processors.postprocess(res.text, metadata)?;
+                for h in &mut res.alternatives {
+                    h.text = self
+                        .processors
+                        .postprocess(std::mem::take(&mut h.text), metadata)?;
+                }
+                if let Some(glossary) = &self.glossary {
+                    res.glossary_report = Some(glossary.check(terms, &res.text));
+                }
+                Ok(res)
+            })
+            .collect()
+    }
+
+    /// Restores the masked spans of a translation and reports the missing and duplicated
+    /// sentinels. If enabled, missing sentinels are first inserted again before the target
+    /// token paying the most attention to their source tokens.
+    fn unmask(
+        &self,
+        masker: &Masker,
+        source: &str,
+        spans: &[String],
+        translation: &mut Translation,
+    ) -> anyhow::Result<()> {
+        let mut reinserted = Vec::new();
+        if masker.reinsertion()
+            && let Some(attention) = &translation.attention
+        {
+            let found = masker
+                .find_sentinels(&translation.text)
+                .into_iter()
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>();
+            let offsets = self
+                .tokenizer
+                .encode_with_offsets(source)
+                .ok()
+                .filter(|o| o.len() == translation.source_tokens);
+            for (idx, (start, end)) in masker.find_sentinels(source) {
+                if found.contains(&idx) || reinserted.contains(&idx) {
+                    continue;
+                }
+                let columns = match &offsets {
+                    Some(offsets) => offsets
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (_, (s, e)))| *s < end && start < *e)
+                        .map(|(i, _)| i)
+                        .collect(),
+                    // Without offsets, assume the tokens are spread evenly over the source.
+                    None => vec![start * translation.source_tokens / source.len().max(1)],
+                };
+                let Some(target) = attention
+                    .iter()
+                    .map(|row| columns.iter().filter_map(|&c| row.get(c)).sum::<f32>())
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .map(|(j, _)| j)
+                else {
+                    continue;
+                };
+                let target = target.min(translation.tokens.len());
+                let prefix = self
+                    .tokenizer
+                    .decode(translation.tokens[..target].to_vec())?;
+                let text = &translation.text;
+                let mut pos = prefix.len().min(text.len());
+                while !text.is_char_boundary(pos) {
+                    pos -= 1;
+                }
+                pos = text.len() - text[pos..].trim_start().len();
+                translation.text.insert_str(pos, &masker.sentinel(idx));
+                reinserted.push(idx);
+            }
+        }
+
+        let (text, mut report) = masker.restore(&translation.text, spans);
+        translation.text = text;
+        for h in &mut translation.alternatives {
+            h.text = masker.restore(&h.text, spans).0;
+        }
+        report.reinserted = reinserted;
+        translation.mask_report = Some(report);
+        Ok(())
+    }
+
+    /// Runs the backend on a tokenized batch and decodes the results.
+    ///
+    /// If `strip_prefixes` is set, the target prefixes are removed from the outputs. Tokens
+    /// for which `skip` returns true are removed before decoding.
+    fn translate_tokens(
+        &self,
+        source: &[Vec<String>],
+        prefixes: Option<&[Vec<String>]>,
+        strip_prefixes: bool,
+        skip: &dyn Fn(&str) -> bool,
+        options: TranslationOptions,
+    ) -> anyhow::Result<Vec<Translation>> {
+        let now = Instant::now();
+        let out = match prefixes {
+            Some(prefixes) => self
+                .t
+                .translate_batch_with_prefixes(source, prefixes, options)?,
+            None => self.t.translate_batch(source, options)?,
+        };
+        let elapsed = now.elapsed();
+
+        out.into_iter()
+            .enumerate()
+            .map(|(idx, r)| {
+                let prefix = match prefixes {
+                    Some(prefixes) if strip_prefixes => prefixes[idx].as_slice(),
+                    _ => &[],
+                };
+                self.to_translation(r, prefix, skip, source[idx].len(), elapsed)
+            })
+            .collect()
+    }
+
+    /// Decodes the hypotheses of a backend result, dropping the leading tokens and attention
+    /// rows that belong to the given target prefix, and the tokens matched by `skip`.
+    fn to_translation(
+        &self,
+        output: BackendOutput,
+        prefix: &[String],
+        skip: &dyn Fn(&str) -> bool,
+        source_tokens: usize,
+        elapsed: Duration,
+    ) -> anyhow::Result<Translation> {
+        let mut best_kept = Vec::new();
+        let tokens = output
+            .hypotheses
+            .into_iter()
+            .enumerate()
+            .map(|(idx, tokens)| {
+                let kept = (0..tokens.len())
+                    .skip(prefix_len_in(&tokens, prefix))
+                    .filter(|&i| !skip(&tokens[i]))
+                    .collect::<Vec<_>>();
+                let tokens = kept.iter().map(|&i| tokens[i].clone()).collect::<Vec<_>>();
+                if idx == 0 {
+                    best_kept = kept;
+                }
+                tokens
+            })
+            .collect::<Vec<_>>();
+        let texts = self
+            .tokenizer
+            .decode_batch(tokens.clone())
+            .map_err(|err| anyhow::anyhow!("failed to decode: {err}"))?;
+        let mut hypotheses =
+            texts
+                .into_iter()
+                .zip(tokens)
+                .enumerate()
+                .map(|(idx, (text, tokens))| Hypothesis {
+                    text,
+                    tokens,
+                    score: output.scores.get(idx).copied(),
+                });
+        let best = hypotheses.next().unwrap_or_default();
+        let attention = output.attention.into_iter().next().map(|mut rows| {
+            best_kept
+                .iter()
+                .filter_map(|&i| rows.get_mut(i).map(std::mem::take))
+                .collect()
+        });
+
+        Ok(Translation {
+            text: best.text,
+            tokens: best.tokens,
+            score: best.score,
+            alternatives: hypotheses.collect(),
+            attention,
+            source_tokens,
+            source_spans: None,
+            mask_report: None,
+            glossary_report: None,
+            elapsed,
+        })
+    }
+}
+
+#[test]
+fn translate_with_mock_backend() {
+    use crate::backend::mock::MockBackend;
+    use crate::tokenizer::whitespace;
+
+    let t = Translator2::with_backend(
+        MockBackend::dictionary([("hallo", "hello"), ("welt", "world")]),
+        whitespace::Tokenizer::new(),
+    );
+    let res = t
+        .translate_batch(&["hallo welt".to_owned()], Default::default())
+        .unwrap();
+    assert_eq!(res[0].text, "hello world");
+    assert_eq!(res[0].tokens, vec!["hello", "world"]);
+    assert_eq!(res[0].source_tokens, 3);
+
+    let options = TranslationOptions {
+        num_hypotheses: 2,
+        return_scores: true,
+        return_attention: true,
+        ..Default::default()
+    };
+    let res = t
+        .translate_batch_with_prefixes(&["welt"], &[vec!["oh"]], options)
+        .unwrap();
+    assert_eq!(res[0].text, "world");
+    assert_eq!(res[0].alternatives.len(), 1);
+    assert!(res[0].score.is_some());
+    assert_eq!(res[0].attention.as_ref().unwrap().len(), 1);
+
+    let res = t
+        .translate_batch_with_text_prefixes(
+            &["hallo welt"],
+            &["well"],
+            PrefixOutput::Full,
+            Default::default(),
+        )
+        .unwrap();
+    assert_eq!(res[0].text, "well hello world");
+
+    let res = t
+        .translate_batch_with_spans(&["hallo  welt"], Default::default())
+        .unwrap();
+    assert_eq!(res[0].source_spans, Some(vec![(0, 5), (7, 11), (11, 11)]));
+    assert_eq!(t.backend().calls(), 4);
+
+    let tokens = |s: &str| s.split(' ').map(str::to_owned).collect::<Vec<_>>();
+    assert_eq!(prefix_len_in(&tokens("oh my world"), &tokens("oh my")), 2);
+    assert_eq!(
+        prefix_len_in(&tokens("oh <unk> world"), &tokens("oh my")),
+        0
+    );
+}
+
+#[test]
+fn translate_to_language() {
+    use crate::backend::mock::MockBackend;
+    use crate::multilingual::ModelFamily;
+    use crate::tokenizer::{SpecialTokens, whitespace};
+
+    let mut t = Translator2::with_backend(
+        MockBackend::dictionary([("__en__", "__de__"), ("hello", "hallo")]),
+        whitespace::Tokenizer::new(),
+    );
+    assert!(
+        t.translate_batch_to(&["hello"], "en", "de", Default::default())
+            .is_err()
+    );
+
+    t.with_languages(Languages::new(ModelFamily::M2m100));
+    let res = t
+        .translate_batch_to(&["hello __add__"], "en", "de", Default::default())
+        .unwrap();
+    assert_eq!(res[0].text, "hallo __add__");
+    assert_eq!(
+        t.backend().batches()[0],
+        vec![vec!["__en__", "hello", "__add__", "</s>"]]
+    );
+
+    let mut tokenizer = whitespace::Tokenizer::new();
+    tokenizer.with_special_tokens(SpecialTokens {
+        source_lang: Some("__en__".to_owned()),
+        ..Default::default()
+    });
+    let mut t = Translator2::with_backend(MockBackend::echo(), tokenizer);
+    t.with_languages(Languages::new(ModelFamily::M2m100));
+    assert!(
+        t.translate_batch_to(&["hello"], "en", "de", Default::default())
+            .is_err()
+    );
+}
+
+#[test]
+fn translate_with_processors() {
+    use crate::backend::mock::MockBackend;
+    use crate::processor::{PunctuationSpacing, Whitespace};
+    use crate::tokenizer::whitespace;
+
+    let mut t = Translator2::with_backend(
+        MockBackend::dictionary([("hallo", "hello"), ("welt", "world")]),
+        whitespace::Tokenizer::new(),
+    );
+    t.with_processor(Whitespace)
+        .with_processor(PunctuationSpacing);
+    let res = t
+        .translate_batch(&["  hallo , welt\n".to_owned()], Default::default())
+        .unwrap();
+    assert_eq!(res[0].text, "  hello, world\n");
+    assert_eq!(
+        t.backend().batches()[0],
+        vec![vec!["hallo", ",", "welt", "</s>"]]
+    );
+}
+
+#[test]
+fn translate_document() {
+    use crate::backend::mock::MockBackend;
+    use crate::tokenizer::whitespace;
+
+    let t = Translator2::with_backend(
+        MockBackend::dictionary([("Hallo.", "Hello."), ("Welt!", "World!")]),
+        whitespace::Tokenizer::new(),
+    );
+    let document = "  Hallo. Welt!\n\n\tHallo.  \n";
+    let options = TranslationOptions {
+        max_batch_size: 2,
+        ..Default::default()
+    };
+    let res = t
+        .translate_document(document, &Segmenter::new("de"), options)
+        .unwrap();
+    assert_eq!(res, "  Hello. World!\n\n\tHello.  \n");
+    assert_eq!(t.backend().calls(), 2);
+
+    let options = TranslationOptions {
+        max_batch_size: 3,
+        batch_type: BatchType::Tokens,
+        ..Default::default()
+    };
+    let res = t
+        .translate_document(document, &Segmenter::new("de"), options)
+        .unwrap();
+    assert_eq!(res, "  Hello. World!\n\n\tHello.  \n");
+    assert_eq!(t.backend().calls(), 5);
+}
+
+#[test]
+fn translate_long_sources() {
+    use crate::backend::mock::MockBackend;
+    use crate::tokenizer::whitespace;
+
+    let mut t = Translator2::with_backend(MockBackend::echo(), whitespace::Tokenizer::new());
+    let options = TranslationOptions {
+        max_input_length: 4,
+        return_attention: true,
+        ..Default::default()
+    };
+    let source = ["a b c. d e f g".to_owned()];
+    let res = t.translate_batch(&source, options.clone()).unwrap();
+    assert_eq!(res[0].text, "a b c. d e f g");
+    assert_eq!(t.backend().calls(), 1);
+
+    t.with_overflow_policy(OverflowPolicy::Error);
+    assert!(t.translate_batch(&source, options.clone()).is_err());
+
+    t.with_overflow_policy(OverflowPolicy::Split);
+    let res = t.translate_batch(&source, options).unwrap();
+    assert_eq!(res[0].text, "a b c. d e f g");
+    assert_eq!(res[0].source_tokens, 10);
+    assert_eq!(
+        t.backend().batches()[1],
+        vec![
+            vec!["a", "b", "c.", "</s>"],
+            vec!["d", "e", "</s>"],
+            vec!["f", "g", "</s>"]
+        ]
+    );
+    let attention = res[0].attention.as_ref().unwrap();
+    assert!(attention.iter().all(|row| row.len() == 10));
+}
+
+#[test]
+fn translate_with_masker() {
+    use crate::backend::mock::MockBackend;
+    use crate::tokenizer::whitespace;
+
+    let mut t = Translator2::with_backend(
+        MockBackend::dictionary([
+            ("Klick", "Click"),
+            ("hier", "here"),
+            ("jetzt", "now"),
+            ("｟1｠", "｟0｠"),
+        ]),
+        whitespace::Tokenizer::new(),
+    );
+    t.with_masker(Masker::default()).unwrap();
+    let source = ["Klick <b> hier </b> jetzt".to_owned()];
+    let res = t.translate_batch(&source, Default::default()).unwrap();
+    assert_eq!(
+        t.backend().batches()[0],
+        vec![vec!["Klick", "｟0｠", "hier", "｟1｠", "jetzt", "</s>"]]
+    );
+    assert_eq!(res[0].text, "Click <b> here  now");
+    let report = res[0].mask_report.as_ref().unwrap();
+    assert_eq!(report.missing, [1]);
+    assert_eq!(report.duplicated, [0]);
+
+    let mut masker = Masker::default();
+    masker.with_reinsertion(true);
+    t.with_masker(masker).unwrap();
+    let options = TranslationOptions {
+        return_attention: true,
+        ..Default::default()
+    };
+    let res = t.translate_batch(&source, options).unwrap();
+    assert_eq!(res[0].text, "Click <b> here </b> now");
+    let report = res[0].mask_report.as_ref().unwrap();
+    assert_eq!(report.reinserted, [1]);
+    assert!(report.missing.is_empty());
+
+    // A tokenizer without the sentinel characters in its vocabulary.
+    struct Ascii;
+
+    impl Tokenizer for Ascii {
+        fn encode(&self, input: &str) -> anyhow::Result<Vec<String>> {
+            Ok(input
+                .split_whitespace()
+                .map(|w| if w.is_ascii() { w } else { "<unk>" }.to_owned())
+                .collect())
+        }
+
+        fn decode(&self, tokens: Vec<String>) -> anyhow::Result<String> {
+            Ok(tokens.join(" "))
+        }
+    }
+
+    let mut t = Translator2::with_backend(MockBackend::echo(), Ascii);
+    assert!(t.with_masker(Masker::default()).is_err());
+    assert!(t.with_glossary(Glossary::new()).is_err());
+    let mut masker = Masker::default();
+    masker.with_sentinels("[", "]");
+    assert!(t.with_masker(masker).is_ok());
 }
 
 #[test]
-fn tessss() {
-    let t = Translator2::new(Path::new("/Users/frederik/code/rust/ctranslate2-src/ctranslate2/model/ja-en-base"), &Default::default(), SentenceTokenizer::new("/Users/frederik/code/rust/ctranslate2-src/ctranslate2/model/spm.nopretok/spm.en.nopretok.model")).unwrap();
-    t.translate_batch(&vec!["Hello World".to_owned()], Default::default());
+fn translate_with_glossary() {
+    use crate::backend::mock::MockBackend;
+    use crate::tokenizer::whitespace;
+
+    let mut t = Translator2::with_backend(
+        MockBackend::dictionary([("Der", "The"), ("ist", "is"), ("gut", "good")]),
+        whitespace::Tokenizer::new(),
+    );
+    let mut glossary = Glossary::new();
+    glossary.with_term("Cloud Server", "Cloud-Server");
+    t.with_glossary(glossary.clone()).unwrap();
+    let source = ["Der cloud servers ist gut".to_owned()];
+    let res = t.translate_batch(&source, Default::default()).unwrap();
+    assert_eq!(
+        t.backend().batches()[0],
+        vec![vec!["Der", "｟T0｠", "ist", "gut", "</s>"]]
+    );
+    assert_eq!(res[0].text, "The Cloud-Server is good");
+    assert!(res[0].glossary_report.as_ref().unwrap().is_ok());
+
+    t.with_glossary(glossary.with_steering(Steering::None).clone())
+        .unwrap();
+    let res = t.translate_batch(&source, Default::default()).unwrap();
+    assert_eq!(res[0].text, "The cloud servers is good");
+    let report = res[0].glossary_report.as_ref().unwrap();
+    assert_eq!(report.missing().count(), 1);
+
+    t.with_glossary(glossary.with_steering(Steering::Prefix).clone())
+        .unwrap();
+    let res = t
+        .translate_batch(&["Cloud Server".to_owned()], Default::default())
+        .unwrap();
+    assert_eq!(res[0].text, "Cloud-Server Cloud Server");
+    assert!(res[0].glossary_report.as_ref().unwrap().is_ok());
+}
+
+#[test]
+fn align_translated_words() {
+    use crate::alignment::to_pharaoh;
+    use crate::backend::mock::MockBackend;
+    use crate::tokenizer::whitespace;
+
+    let t = Translator2::with_backend(
+        MockBackend::dictionary([("Hallo", "Hello"), ("Welt", "world")]),
+        whitespace::Tokenizer::new(),
+    );
+    let options = TranslationOptions {
+        return_attention: true,
+        ..Default::default()
+    };
+    let res = t
+        .translate_batch_with_spans(&["Hallo  Welt"], options)
+        .unwrap();
+    let pairs = t
+        .align_words("Hallo  Welt", &res[0], AlignmentStrategy::Argmax)
+        .unwrap();
+    assert_eq!(to_pharaoh(&pairs), "0-0 1-1");
+
+    let res = t
+        .translate_batch_with_spans(&["Hallo"], Default::default())
+        .unwrap();
+    assert!(
+        t.align_words("Hallo", &res[0], AlignmentStrategy::Argmax)
+            .is_err()
+    );
+}
+
+#[test]
+fn translate_with_cache() {
+    use std::sync::Arc;
+
+    use crate::backend::mock::MockBackend;
+    use crate::cache::{CacheStats, LruStore};
+    use crate::processor::Quotes;
+    use crate::tokenizer::whitespace;
+
+    let mut t = Translator2::with_backend(MockBackend::echo(), whitespace::Tokenizer::new());
+    t.with_cache(Cache::in_memory("echo", 10));
+    let res = t
+        .translate_batch(&["a b".to_owned(), "c".to_owned()], Default::default())
+        .unwrap();
+    assert_eq!(res[1].text, "c");
+    let res = t
+        .translate_batch(
+            &["c".to_owned(), "d".to_owned(), "a b".to_owned()],
+            Default::default(),
+        )
+        .unwrap();
+    let texts = res.iter().map(|r| r.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(texts, ["c", "d", "a b"]);
+    assert_eq!(t.backend().batches()[1], vec![vec!["d", "</s>"]]);
+    assert_eq!(
+        t.cache().unwrap().stats(),
+        CacheStats { hits: 2, misses: 3 }
+    );
+
+    let options = TranslationOptions {
+        beam_size: 4,
+        ..Default::default()
+    };
+    t.translate_batch(&["c".to_owned()], options).unwrap();
+    assert_eq!(t.backend().calls(), 3);
+
+    let store = Arc::new(LruStore::new(10));
+    let source = ["\u{201c}a\u{201d}".to_owned()];
+    let mut plain = Translator2::with_backend(MockBackend::echo(), whitespace::Tokenizer::new());
+    plain.with_cache(Cache::new("echo", store.clone()));
+    let mut quotes = Translator2::with_backend(MockBackend::echo(), whitespace::Tokenizer::new());
+    quotes
+        .with_processor(Quotes)
+        .with_cache(Cache::new("echo", store.clone()));
+    let res = plain.translate_batch(&source, Default::default()).unwrap();
+    assert_eq!(res[0].text, "\u{201c}a\u{201d}");
+    let res = quotes.translate_batch(&source, Default::default()).unwrap();
+    assert_eq!(res[0].text, "\"a\"");
+    assert_eq!(quotes.cache().unwrap().stats().hits, 0);
+    assert_eq!(store.len(), 2);
+}
+
+#[test]
+fn translate_duplicates() {
+    use crate::backend::mock::MockBackend;
+    use crate::tokenizer::whitespace;
+
+    let mut t = Translator2::with_backend(MockBackend::reverse(), whitespace::Tokenizer::new());
+    let sources = ["a b", "c", "a b", "Cafe\u{301}", "Café"].map(String::from);
+    let res = t.translate_batch(&sources, Default::default()).unwrap();
+    let texts = res.iter().map(|r| r.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(texts, ["b a", "c", "b a", "Cafe\u{301}", "Cafe\u{301}"]);
+    assert_eq!(
+        t.backend().batches()[0],
+        vec![
+            vec!["a", "b", "</s>"],
+            vec!["c", "</s>"],
+            vec!["Cafe\u{301}", "</s>"]
+        ]
+    );
+
+    let sampling = TranslationOptions {
+        sampling_topk: 10,
+        ..Default::default()
+    };
+    t.translate_batch(&sources, sampling).unwrap();
+    assert_eq!(t.backend().batches()[1].len(), 5);
+
+    t.with_deduplication(false);
+    t.translate_batch(&sources, Default::default()).unwrap();
+    assert_eq!(t.backend().batches()[2].len(), 5);
 }
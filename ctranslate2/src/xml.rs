@@ -0,0 +1,148 @@
+//! A minimal XML reader for the rule and glossary files.
+//!
+//! It reads well-formed documents without a DTD: elements, attributes, text, CDATA sections
+//! and the predefined and numeric entities. Processing instructions, comments and declarations
+//! are skipped.
+
+pub(crate) fn attr<'a>(attrs: &'a [(&str, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+/// A piece of an XML document. Tag names are given without their namespace prefix.
+pub(crate) enum Event<'a> {
+    Start(&'a str, Vec<(&'a str, String)>),
+    End(&'a str),
+    Text(String),
+}
+
+/// A minimal XML reader, sufficient for SRX and TBX files.
+pub(crate) struct Events<'a> {
+    rest: &'a str,
+    pending_end: Option<&'a str>,
+}
+
+impl<'a> Events<'a> {
+    pub(crate) fn new(content: &'a str) -> Self {
+        Self {
+            rest: content,
+            pending_end: None,
+        }
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, String> {
+        let idx = self
+            .rest
+            .find(end)
+            .ok_or_else(|| format!("unterminated XML construct, expected {end:?}"))?;
+        let res = &self.rest[..idx];
+        self.rest = &self.rest[idx + end.len()..];
+        Ok(res)
+    }
+
+    fn tag(&mut self) -> Result<Event<'a>, String> {
+        let tag = self.skip_past(">")?;
+        if let Some(name) = tag.strip_prefix('/') {
+            return Ok(Event::End(local_name(name.trim())));
+        }
+        let (tag, empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let (name, mut rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let name = local_name(name);
+
+        let mut attrs = Vec::new();
+        while let Some((key, value)) = rest.split_once('=') {
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|&q| q == '"' || q == '\'')
+                .ok_or_else(|| format!("unquoted attribute in <{name}>"))?;
+            let (value, tail) = value[1..]
+                .split_once(quote)
+                .ok_or_else(|| format!("unterminated attribute in <{name}>"))?;
+            attrs.push((local_name(key.trim()), unescape(value)?));
+            rest = tail;
+        }
+        if empty {
+            self.pending_end = Some(name);
+        }
+        Ok(Event::Start(name, attrs))
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<Event<'a>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(name) = self.pending_end.take() {
+            return Some(Ok(Event::End(name)));
+        }
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            if let Some(rest) = self.rest.strip_prefix("<![CDATA[") {
+                self.rest = rest;
+                return Some(self.skip_past("]]>").map(|t| Event::Text(t.to_owned())));
+            }
+            let skipped = [("<?", "?>"), ("<!--", "-->"), ("<!", ">")]
+                .into_iter()
+                .find(|(start, _)| self.rest.starts_with(start));
+            if let Some((start, end)) = skipped {
+                self.rest = &self.rest[start.len()..];
+                if let Err(err) = self.skip_past(end) {
+                    return Some(Err(err));
+                }
+                continue;
+            }
+            if let Some(rest) = self.rest.strip_prefix('<') {
+                self.rest = rest;
+                return Some(self.tag());
+            }
+            let idx = self.rest.find('<').unwrap_or(self.rest.len());
+            let text = &self.rest[..idx];
+            self.rest = &self.rest[idx..];
+            return Some(unescape(text).map(Event::Text));
+        }
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+/// Replaces the XML entities and character references of a text.
+fn unescape(text: &str) -> Result<String, String> {
+    let mut res = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(idx) = rest.find('&') {
+        res.push_str(&rest[..idx]);
+        let (entity, tail) = rest[idx + 1..]
+            .split_once(';')
+            .ok_or_else(|| format!("unterminated entity in {text:?}"))?;
+        let c = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+            }
+            .and_then(char::from_u32),
+        };
+        res.push(c.ok_or_else(|| format!("unknown entity &{entity};"))?);
+        rest = tail;
+    }
+    res.push_str(rest);
+    Ok(res)
+}